@@ -0,0 +1,75 @@
+use std::{path::Path, time::SystemTime};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Everything a cached result was computed from, so it is only reused when none of it changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// When the config was last changed.
+    pub config_modified: SystemTime,
+    pub date: NaiveDate,
+    pub balance: Decimal,
+    /// The options the result was computed with.
+    pub options: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    key: CacheKey,
+    value: T,
+}
+
+/// The value cached at `path` for `key`. A missing or unreadable cache, or one stored for a
+/// different key, is treated as a miss.
+pub fn load<T: DeserializeOwned>(path: &Path, key: &CacheKey) -> Option<T> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let entry: Entry<T> = serde_json::from_str(&contents).ok()?;
+    (entry.key == *key).then_some(entry.value)
+}
+
+/// Replaces whatever is cached at `path` with `value`.
+pub fn store<T: Serialize>(path: &Path, key: &CacheKey, value: &T) -> Result<()> {
+    let entry = Entry {
+        key: key.clone(),
+        value,
+    };
+    std::fs::write(path, serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn key(config_modified: SystemTime) -> CacheKey {
+        CacheKey {
+            config_modified,
+            date: NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            balance: Decimal::new(10000, 2),
+            options: "--reset-day 18".to_owned(),
+        }
+    }
+
+    #[test]
+    fn changed_config_busts_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("compute.cache");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_675_000_000);
+
+        assert_eq!(load::<Vec<String>>(&path, &key(modified)), None);
+
+        store(&path, &key(modified), &vec!["£29.50".to_owned()]).unwrap();
+        assert_eq!(
+            load::<Vec<String>>(&path, &key(modified)),
+            Some(vec!["£29.50".to_owned()])
+        );
+
+        let stale = key(modified + Duration::from_secs(60));
+        assert_eq!(load::<Vec<String>>(&path, &stale), None);
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+
+/// Programs that print the clipboard's text, tried in order until one works.
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+    &["pbpaste"],
+    &["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+/// Text on the system clipboard, read with the first paste program that is installed.
+///
+/// A program that is installed but fails is reported rather than skipped, as is an empty
+/// clipboard.
+pub fn read() -> Result<String> {
+    for command in PASTE_COMMANDS {
+        let Ok(output) = std::process::Command::new(command[0])
+            .args(&command[1..])
+            .output()
+        else {
+            continue;
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "{} couldn't read the clipboard: {}",
+                command[0],
+                stderr.trim()
+            ));
+        }
+        let text = String::from_utf8(output.stdout)
+            .map_err(|_| anyhow!("the clipboard doesn't contain text"))?;
+        if text.trim().is_empty() {
+            return Err(anyhow!("the clipboard is empty"));
+        }
+        return Ok(text);
+    }
+    let (last, rest) = PASTE_COMMANDS
+        .split_last()
+        .expect("there is at least one paste command");
+    let tried: Vec<&str> = rest.iter().map(|command| command[0]).collect();
+    Err(anyhow!(
+        "couldn't read the clipboard with any of {} or {}, install one of them",
+        tried.join(", "),
+        last[0]
+    ))
+}
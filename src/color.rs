@@ -0,0 +1,88 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::payment::deserialize_amount;
+
+/// Balances at or above `high` are healthy, balances below `low` are in trouble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorThresholds {
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub low: Decimal,
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub high: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorBand {
+    Green,
+    Amber,
+    Red,
+}
+
+impl ColorThresholds {
+    pub fn band(&self, balance: Decimal) -> ColorBand {
+        if balance >= self.high {
+            ColorBand::Green
+        } else if balance < self.low {
+            ColorBand::Red
+        } else {
+            ColorBand::Amber
+        }
+    }
+}
+
+impl ColorBand {
+    const fn code(self) -> &'static str {
+        match self {
+            Self::Green => "32",
+            Self::Amber => "33",
+            Self::Red => "31",
+        }
+    }
+
+    /// `text` wrapped in the ANSI escape codes for this band.
+    pub fn paint(self, text: &str) -> String {
+        format!("\x1b[{}m{text}\x1b[0m", self.code())
+    }
+}
+
+/// Whether output should be coloured, following <https://no-color.org>.
+pub fn enabled(no_color: bool, is_terminal: bool) -> bool {
+    is_terminal && !no_color && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balances_map_to_bands() {
+        let thresholds = ColorThresholds {
+            low: Decimal::new(50, 0),
+            high: Decimal::new(200, 0),
+        };
+        let bands: Vec<ColorBand> = [-10, 0, 49, 50, 150, 199, 200, 1000]
+            .into_iter()
+            .map(|b| thresholds.band(Decimal::new(b, 0)))
+            .collect();
+
+        assert_eq!(
+            bands,
+            vec![
+                ColorBand::Red,
+                ColorBand::Red,
+                ColorBand::Red,
+                ColorBand::Amber,
+                ColorBand::Amber,
+                ColorBand::Amber,
+                ColorBand::Green,
+                ColorBand::Green,
+            ]
+        );
+    }
+
+    #[test]
+    fn paint_wraps_in_escape_codes() {
+        assert_eq!(ColorBand::Red.paint("£1.00"), "\x1b[31m£1.00\x1b[0m");
+    }
+}
@@ -0,0 +1,68 @@
+use crate::payment::{self, Payment, Payments, MAX_AMOUNT_SCALE};
+
+/// A check for a kind of bad data in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Amounts with more decimal places than money is shown with.
+    Precision,
+}
+
+impl Rule {
+    pub const ALL: [Self; 1] = [Self::Precision];
+
+    /// A line describing each payment that breaks the rule.
+    pub fn check(self, payments: &[Payment]) -> Vec<String> {
+        match self {
+            Self::Precision => payments
+                .iter()
+                .filter(|p| p.amount.scale() > MAX_AMOUNT_SCALE)
+                .map(|p| {
+                    format!(
+                        "{}: `{}` has more than {MAX_AMOUNT_SCALE} decimal places",
+                        p.name, p.amount
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Changes the payments that break the rule so that they no longer do.
+    pub fn fix(self, mut payments: Payments) -> Payments {
+        match self {
+            Self::Precision => {
+                for p in payments
+                    .iter_mut()
+                    .filter(|p| p.amount.scale() > MAX_AMOUNT_SCALE)
+                {
+                    p.amount = payment::normalize_amount(p.amount);
+                }
+            }
+        }
+        payments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    #[test]
+    fn precision_rule_finds_and_fixes_long_amounts() {
+        let payments = vec![
+            Payment::new("Gas".to_owned(), Decimal::new(204550, 4), 6),
+            Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3),
+        ];
+
+        assert_eq!(
+            Rule::Precision.check(&payments),
+            vec!["Gas: `20.4550` has more than 2 decimal places"]
+        );
+
+        let fixed = Rule::Precision.fix(payments);
+        assert_eq!(fixed[0].amount.to_string(), "20.46");
+        assert_eq!(fixed[1].amount.to_string(), "15.00");
+        assert!(Rule::Precision.check(&fixed).is_empty());
+    }
+}
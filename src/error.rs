@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use serde_json::json;
+
+/// Errors with a stable code, so scripts using `--error-format json` can tell them apart.
+#[derive(Debug)]
+pub enum BalanceError {
+    /// A named bill or category doesn't exist.
+    NotFound(String),
+    /// A bill with the same name already exists.
+    AlreadyExists(String),
+    /// A value failed validation.
+    Invalid(String),
+}
+
+impl BalanceError {
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::AlreadyExists(_) => "already_exists",
+            Self::Invalid(_) => "invalid",
+        }
+    }
+}
+
+impl Display for BalanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(m) | Self::AlreadyExists(m) | Self::Invalid(m) => write!(f, "{m}"),
+        }
+    }
+}
+
+impl std::error::Error for BalanceError {}
+
+/// Code of the first [`BalanceError`] in the error's chain, or `error` for anything else.
+pub fn error_code(error: &anyhow::Error) -> &'static str {
+    error
+        .chain()
+        .find_map(|e| e.downcast_ref::<BalanceError>())
+        .map_or("error", BalanceError::code)
+}
+
+/// The error as a single line JSON object with its code and message.
+pub fn error_json(error: &anyhow::Error) -> String {
+    json!({
+        "code": error_code(error),
+        "message": format!("{error:#}"),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn codes_found_through_context() {
+        let error = Err::<(), _>(BalanceError::Invalid("bad".to_owned()))
+            .context("loading")
+            .unwrap_err();
+
+        assert_eq!(error_code(&error), "invalid");
+        assert_eq!(error_code(&anyhow::anyhow!("other")), "error");
+    }
+}
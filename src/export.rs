@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde_yaml::Value;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+
+use crate::payment::{Payment, Payments};
+
+fn key(key: &Value) -> Result<&str> {
+    key.as_str()
+        .ok_or_else(|| anyhow!("only string keys can be written as TOML"))
+}
+
+fn toml_value(value: &Value) -> Result<toml_edit::Value> {
+    Ok(match value {
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n
+                .as_f64()
+                .ok_or_else(|| anyhow!("{n} can't be written as TOML"))?
+                .into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Sequence(values) => {
+            let mut array = Array::new();
+            for v in values {
+                array.push(toml_value(v)?);
+            }
+            array.into()
+        }
+        Value::Mapping(mapping) => {
+            let mut table = InlineTable::new();
+            for (k, v) in mapping {
+                table.insert(key(k)?, toml_value(v)?);
+            }
+            table.into()
+        }
+        Value::Null | Value::Tagged(_) => {
+            return Err(anyhow!("{value:?} can't be written as TOML"))
+        }
+    })
+}
+
+/// Writes payments as `[[payments]]` tables, which can be pasted into a TOML config.
+///
+/// Payments go through their serde representation, so fields keep the order they are stored in
+/// and amounts are written as strings with their exact precision.
+pub fn payments_toml(payments: &[&Payment]) -> Result<String> {
+    let mut tables = ArrayOfTables::new();
+    for payment in payments {
+        let Value::Mapping(fields) = serde_yaml::to_value(payment)? else {
+            unreachable!("a payment always serialises to a mapping");
+        };
+        let mut table = Table::new();
+        for (k, v) in &fields {
+            table.insert(key(k)?, Item::Value(toml_value(v)?));
+        }
+        tables.push(table);
+    }
+
+    let mut document = DocumentMut::new();
+    document.insert("payments", Item::ArrayOfTables(tables));
+    Ok(document.to_string())
+}
+
+/// Copies of the payments that are safe to share, with names replaced by `bill1`, `bill2` and so
+/// on, categories by `category1`, `category2` and so on, and amounts rounded up to the next ten.
+///
+/// Days, frequencies and which bills share a category are kept, descriptions are dropped.
+pub fn anonymize(payments: &[&Payment]) -> Payments {
+    let mut categories: Vec<&str> = vec![];
+    payments
+        .iter()
+        .enumerate()
+        .map(|(i, payment)| {
+            let category = payment.category.as_deref().map(|c| {
+                let index = categories.iter().position(|k| *k == c).unwrap_or_else(|| {
+                    categories.push(c);
+                    categories.len() - 1
+                });
+                format!("category{}", index + 1)
+            });
+            let mut amount = (payment.amount / Decimal::TEN).ceil() * Decimal::TEN;
+            amount.rescale(2);
+            Payment {
+                name: format!("bill{}", i + 1),
+                amount,
+                category,
+                description: None,
+                change: None,
+                ..(*payment).clone()
+            }
+        })
+        .collect()
+}
+
+/// `field` quoted if it contains the delimiter, a quote or a line break, so it reads back whole.
+fn csv_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Writes payments as CSV with a `name,amount,day_paid,category` header, separated by
+/// `delimiter`, in the form `import` reads.
+pub fn payments_csv(payments: &[&Payment], delimiter: char) -> String {
+    let separator = delimiter.to_string();
+    let mut lines = vec![["name", "amount", "day_paid", "category"].join(&separator)];
+    for payment in payments {
+        let fields = [
+            csv_field(&payment.name, delimiter),
+            payment.amount.to_string(),
+            payment.day_paid.to_string(),
+            csv_field(payment.category.as_deref().unwrap_or_default(), delimiter),
+        ];
+        lines.push(fields.join(&separator));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment::example_config;
+
+    /// The YAML form of a value read back from TOML, the reverse of [`toml_value`].
+    fn yaml_value(value: &toml_edit::Value) -> Value {
+        match value {
+            toml_edit::Value::String(s) => s.value().clone().into(),
+            toml_edit::Value::Integer(i) => (*i.value()).into(),
+            toml_edit::Value::Float(f) => (*f.value()).into(),
+            toml_edit::Value::Boolean(b) => (*b.value()).into(),
+            toml_edit::Value::Datetime(d) => d.value().to_string().into(),
+            toml_edit::Value::Array(array) => array.iter().map(yaml_value).collect(),
+            toml_edit::Value::InlineTable(table) => Value::Mapping(
+                table
+                    .iter()
+                    .map(|(k, v)| (k.into(), yaml_value(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Payments read back from the `[[payments]]` tables, deserialised from their YAML form.
+    fn parse_payments(toml: &str) -> Payments {
+        let document: DocumentMut = toml.parse().unwrap();
+        document["payments"]
+            .as_array_of_tables()
+            .unwrap()
+            .iter()
+            .map(|table| {
+                let fields = table
+                    .iter()
+                    .map(|(k, item)| (k.into(), yaml_value(item.as_value().unwrap())))
+                    .collect();
+                serde_yaml::from_value(Value::Mapping(fields)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_payments_through_toml() {
+        let payments = vec![
+            Payment::new("Gas".to_owned(), Decimal::new(20455, 3), 6).with_category("Energy"),
+            example_config().payments.remove(0),
+        ];
+
+        let toml = payments_toml(&payments.iter().collect::<Vec<_>>()).unwrap();
+
+        assert!(toml.starts_with("[[payments]]\nname = \"Gas\"\namount = \"20.455\"\n"));
+        let parsed = parse_payments(&toml);
+        // Payments compare equal by name alone, so every field is compared through `Debug`.
+        assert_eq!(format!("{parsed:?}"), format!("{payments:?}"));
+    }
+
+    #[test]
+    fn export_csv_with_custom_delimiter() {
+        let payments = [
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6).with_category("Energy"),
+            Payment::new("Water; Sewage".to_owned(), Decimal::new(1500, 2), 3),
+        ];
+
+        let csv = payments_csv(&payments.iter().collect::<Vec<_>>(), ';');
+
+        assert_eq!(
+            csv,
+            "name;amount;day_paid;category\nGas;20.00;6;Energy\n\"Water; Sewage\";15.00;3;\n"
+        );
+        let parsed = crate::import::parse_payments(&csv, ';', false, &[]).unwrap();
+        assert_eq!(parsed[1].name, "Water; Sewage");
+    }
+
+    #[test]
+    fn anonymize_masks_names_and_keeps_structure() {
+        let payments = [
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6).with_category("Energy"),
+            Payment::new("Netflix".to_owned(), Decimal::new(1099, 2), 12).with_category("Fun"),
+            Payment::new("Electric".to_owned(), Decimal::new(3550, 2), 8).with_category("Energy"),
+            Payment {
+                description: Some("Flat 2".to_owned()),
+                ..Payment::new("Rent".to_owned(), Decimal::new(4, 0), 1)
+            },
+        ];
+
+        let anonymized = anonymize(&payments.iter().collect::<Vec<_>>());
+        let csv = payments_csv(&anonymized.iter().collect::<Vec<_>>(), ',');
+
+        assert_eq!(
+            csv,
+            "name,amount,day_paid,category
+bill1,20.00,6,category1
+bill2,20.00,12,category2
+bill3,40.00,8,category1
+bill4,10.00,1,
+"
+        );
+        assert_eq!(anonymized[3].description, None);
+    }
+}
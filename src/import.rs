@@ -0,0 +1,373 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::{
+    error::BalanceError,
+    payment::{Payment, Payments, PaymentsExt, DAYS_PAID_RANGE, MAX_AMOUNT_SCALE},
+};
+
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.into_iter().map(|f| f.trim().to_owned()).collect()
+}
+
+struct Columns {
+    name: usize,
+    amount: usize,
+    day_paid: usize,
+    category: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let position = |column: &str| header.iter().position(|h| h == column);
+        let required =
+            |column: &str| position(column).ok_or_else(|| anyhow!("missing `{column}` column"));
+
+        Ok(Self {
+            name: required("name")?,
+            amount: required("amount")?,
+            day_paid: required("day_paid")?,
+            category: position("category"),
+        })
+    }
+}
+
+fn parse_amount(s: &str, round: bool) -> Result<Decimal> {
+    let amount: Decimal = s.parse().map_err(|_| anyhow!("`{s}` isn't a Decimal"))?;
+
+    if round {
+        Ok(amount.round_dp_with_strategy(MAX_AMOUNT_SCALE, RoundingStrategy::MidpointAwayFromZero))
+    } else {
+        Ok(amount)
+    }
+}
+
+fn parse_day_paid(s: &str) -> Result<isize> {
+    s.parse().map_err(|_| anyhow!("`{s}` isn't an integer"))
+}
+
+fn parse_record(columns: &Columns, record: &[String], round: bool) -> Result<Payment> {
+    let field = |index: usize| {
+        record
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("expected at least {} fields", index + 1))
+    };
+
+    let category = match columns.category {
+        Some(index) => Some(field(index)?).filter(|c| !c.is_empty()),
+        None => None,
+    };
+
+    Payment::builder()
+        .name(field(columns.name)?)
+        .amount(parse_amount(field(columns.amount)?, round)?)
+        .day_paid(parse_day_paid(field(columns.day_paid)?)?)
+        .category(category)
+        .build()
+}
+
+/// Parses CSV text with a `name,amount,day_paid` header, and an optional `category` column,
+/// with fields separated by `delimiter`.
+///
+/// Every row is validated before returning, so all invalid rows are reported together. Names
+/// must be unique within the file and must not already be in `existing`.
+pub fn parse_payments(
+    contents: &str,
+    delimiter: char,
+    round: bool,
+    existing: &[Payment],
+) -> Result<Payments> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+
+    let (_, header) = lines.next().ok_or_else(|| anyhow!("no header row found"))?;
+    let columns = Columns::from_header(&split_record(header, delimiter))?;
+
+    let existing = existing.index();
+    let mut payments = Payments::new();
+    let mut errors = vec![];
+    for (i, line) in lines {
+        let payment = parse_record(&columns, &split_record(line, delimiter), round).and_then(|p| {
+            if existing.contains_key(p.name.as_str()) || payments.contains(&p) {
+                Err(BalanceError::AlreadyExists(format!("{} already exists", p.name)).into())
+            } else {
+                Ok(p)
+            }
+        });
+        match payment {
+            Ok(p) => payments.push(p),
+            Err(e) => errors.push(format!("line {}: {e}", i + 1)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(payments)
+    } else {
+        Err(BalanceError::Invalid(format!("invalid rows found:\n{}", errors.join("\n"))).into())
+    }
+}
+
+#[derive(Default)]
+struct QifTransaction {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+}
+
+/// Day of the month from a QIF date, which is either `MM/DD/YY(YY)` or `YYYY-MM-DD`.
+fn parse_qif_day(date: &str) -> Result<isize> {
+    let parts: Vec<&str> = date.split(['/', '-', '\'', '.']).map(str::trim).collect();
+    let day = match parts.as_slice() {
+        [year, _, day] if year.len() == 4 => day,
+        [_, day, _] => day,
+        _ => return Err(anyhow!("`{date}` isn't a QIF date")),
+    };
+    let day = parse_day_paid(day)?;
+
+    Ok(day.min(*DAYS_PAID_RANGE.end()))
+}
+
+impl QifTransaction {
+    /// The payment this transaction suggests, or `None` if money was paid in rather than out.
+    fn candidate(&self, round: bool) -> Result<Option<Payment>> {
+        let name = self
+            .memo
+            .as_deref()
+            .or(self.payee.as_deref())
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| anyhow!("transaction has no memo or payee"))?;
+        let amount = self
+            .amount
+            .as_deref()
+            .ok_or_else(|| anyhow!("transaction has no amount"))?;
+        let amount = parse_amount(&amount.replace(',', ""), round)?;
+        if amount >= Decimal::ZERO {
+            return Ok(None);
+        }
+        let date = self
+            .date
+            .as_deref()
+            .ok_or_else(|| anyhow!("transaction has no date"))?;
+
+        Payment::builder()
+            .name(name)
+            .amount(-amount)
+            .day_paid(parse_qif_day(date)?)
+            .build()
+            .map(Some)
+    }
+}
+
+/// Parses a QIF bank export into candidate payments, one for each memo, or payee when there is no
+/// memo, that money was paid out to.
+///
+/// When the same name is paid more than once the latest transaction is used. Names already in
+/// `existing` are reported as errors, as with CSV imports.
+pub fn parse_qif(contents: &str, round: bool, existing: &[Payment]) -> Result<Payments> {
+    let existing = existing.index();
+    let mut payments = Payments::new();
+    let mut errors = vec![];
+    let mut transaction = QifTransaction::default();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = Some(line[code.len_utf8()..].trim().to_owned());
+        match code {
+            'D' => transaction.date = value,
+            'T' | 'U' => transaction.amount = value,
+            'P' => transaction.payee = value,
+            'M' => transaction.memo = value,
+            '^' => match std::mem::take(&mut transaction).candidate(round) {
+                Ok(Some(p)) if existing.contains_key(p.name.as_str()) => {
+                    errors.push(format!("line {}: {} already exists", i + 1, p.name));
+                }
+                Ok(Some(p)) => match payments.iter_mut().find(|c| **c == p) {
+                    Some(candidate) => *candidate = p,
+                    None => payments.push(p),
+                },
+                Ok(None) => {}
+                Err(e) => errors.push(format!("line {}: {e}", i + 1)),
+            },
+            _ => {}
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(payments)
+    } else {
+        Err(BalanceError::Invalid(format!(
+            "invalid transactions found:\n{}",
+            errors.join("\n")
+        ))
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_quoted_fields() {
+        let payments = parse_payments(
+            "name,amount,day_paid\n\"Gas, Electric\",20.00,6\n",
+            ',',
+            false,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(payments[0].name, "Gas, Electric");
+        assert_eq!(payments[0].amount, Decimal::new(2000, 2));
+        assert_eq!(payments[0].day_paid, 6);
+    }
+
+    #[test]
+    fn parse_semicolon_delimited() {
+        let payments = parse_payments(
+            "name;amount;day_paid;category\nGas;20.00;6;Energy\n\"Water; Sewage\";15.00;3;\n",
+            ';',
+            false,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].category.as_deref(), Some("Energy"));
+        assert_eq!(payments[1].name, "Water; Sewage");
+        assert_eq!(payments[1].amount, Decimal::new(1500, 2));
+        assert_eq!(payments[1].category, None);
+    }
+
+    #[test]
+    fn round_four_decimal_amount() {
+        let payments =
+            parse_payments("name,amount,day_paid\nGas,20.4550,6\n", ',', true, &[]).unwrap();
+
+        assert_eq!(payments[0].amount, Decimal::new(2046, 2));
+    }
+
+    #[test]
+    fn reject_four_decimal_amount_without_round() {
+        let error =
+            parse_payments("name,amount,day_paid\nGas,20.4550,6\n", ',', false, &[]).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid rows found:\nline 2: `20.4550` has more than 2 decimal places"
+        );
+    }
+
+    #[test]
+    fn report_all_invalid_rows() {
+        let existing = vec![Payment {
+            name: "Water".to_owned(),
+            amount: Decimal::new(1500, 2),
+            day_paid: 3,
+            ..Payment::default()
+        }];
+        let contents = "name,amount,day_paid
+Gas,abc,6
+Phone,10.00,6
+Electric,30.00,31
+Phone,12.00,7
+Water,15.00,3
+";
+
+        let error = parse_payments(contents, ',', false, &existing).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid rows found:
+line 2: `abc` isn't a Decimal
+line 4: days paid not in range 1-28
+line 5: Phone already exists
+line 6: Water already exists"
+        );
+    }
+
+    #[test]
+    fn parse_qif_sample() {
+        let contents = "!Type:Bank
+D01/06/2023
+T-20.00
+PBritish Gas
+MGas
+^
+D01/18/2023
+T1,500.00
+PEmployer
+^
+D2023-01-31
+T-9.99
+PStreamCo
+^
+D02/06/2023
+T-22.50
+PBritish Gas
+MGas
+^
+";
+
+        let payments = parse_qif(contents, false, &[]).unwrap();
+
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].name, "Gas");
+        assert_eq!(payments[0].amount, Decimal::new(2250, 2));
+        assert_eq!(payments[0].day_paid, 6);
+        assert_eq!(payments[1].name, "StreamCo");
+        assert_eq!(payments[1].amount, Decimal::new(999, 2));
+        assert_eq!(payments[1].day_paid, 28);
+    }
+
+    #[test]
+    fn report_invalid_qif_transactions() {
+        let existing = vec![Payment {
+            name: "Gas".to_owned(),
+            ..Payment::default()
+        }];
+        let contents = "!Type:Bank
+D01/06/2023
+T-20.00
+MGas
+^
+D01/07/2023
+PWater
+^
+";
+
+        let error = parse_qif(contents, false, &existing).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid transactions found:
+line 5: Gas already exists
+line 8: transaction has no amount"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{money::Position, payment::Config};
+
+/// Conventions for showing money and dates in a region, named by a tag such as `en-GB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub tag: &'static str,
+    pub currency_position: Position,
+    pub group_separator: char,
+    pub decimal_separator: char,
+    pub date_format: &'static str,
+}
+
+const LOCALES: &[Locale] = &[
+    Locale {
+        tag: "en-GB",
+        currency_position: Position::Prefix,
+        group_separator: ',',
+        decimal_separator: '.',
+        date_format: "%d/%m/%Y",
+    },
+    Locale {
+        tag: "en-US",
+        currency_position: Position::Prefix,
+        group_separator: ',',
+        decimal_separator: '.',
+        date_format: "%m/%d/%Y",
+    },
+    Locale {
+        tag: "de-DE",
+        currency_position: Position::Suffix,
+        group_separator: '.',
+        decimal_separator: ',',
+        date_format: "%d.%m.%Y",
+    },
+    Locale {
+        tag: "fr-FR",
+        currency_position: Position::Suffix,
+        group_separator: ' ',
+        decimal_separator: ',',
+        date_format: "%d/%m/%Y",
+    },
+];
+
+impl Locale {
+    /// The locale for `tag`, ignoring case and accepting `_` in place of `-`.
+    pub fn parse(tag: &str) -> Result<Self, String> {
+        let normalised = tag.trim().replace('_', "-");
+        LOCALES
+            .iter()
+            .find(|l| l.tag.eq_ignore_ascii_case(&normalised))
+            .copied()
+            .ok_or_else(|| {
+                let tags: Vec<&str> = LOCALES.iter().map(|l| l.tag).collect();
+                format!(
+                    "unknown locale `{tag}`, expected one of {}",
+                    tags.join(", ")
+                )
+            })
+    }
+
+    /// `config` with its number and date formatting replaced by this locale's, grouping thousands.
+    pub fn apply(&self, config: Config) -> Config {
+        Config {
+            currency_position: self.currency_position,
+            grouped: true,
+            group_separator: self.group_separator,
+            decimal_separator: self.decimal_separator,
+            date_format: self.date_format.to_owned(),
+            ..config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn locales_format_numbers_and_dates_differently() {
+        let amount = Decimal::new(123_456, 2);
+        let date = NaiveDate::from_ymd_opt(2023, 2, 5).unwrap();
+        let german = Locale::parse("de-DE").unwrap().apply(Config::default());
+        let british = Locale::parse("en_gb").unwrap().apply(Config::default());
+
+        assert_eq!(german.money_format().format_number(amount), "1.234,56");
+        assert_eq!(german.money_format().format(amount), "1.234,56£");
+        assert_eq!(british.money_format().format(amount), "£1,234.56");
+        assert_eq!(
+            utils::format_date(&date, &german.date_format).unwrap(),
+            "05.02.2023"
+        );
+        assert_eq!(
+            utils::format_date(&date, &british.date_format).unwrap(),
+            "05/02/2023"
+        );
+    }
+
+    #[test]
+    fn unknown_locale_lists_known_tags() {
+        assert_eq!(
+            Locale::parse("xx-XX").unwrap_err(),
+            "unknown locale `xx-XX`, expected one of en-GB, en-US, de-DE, fr-FR"
+        );
+    }
+}
@@ -1,22 +1,90 @@
 #![warn(clippy::all, clippy::nursery)]
 
-use std::ops::RangeInclusive;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::IsTerminal,
+    ops::Neg,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::OnceLock,
+};
 
-use clap::{Args, Parser, Subcommand};
-use payment::{edit_config, store_config, Payments};
-use rust_decimal::Decimal;
+use chrono::{Datelike, NaiveDate};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    ArgGroup, Args, Parser, Subcommand, ValueEnum,
+};
+use payment::{Payment, Payments};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
 
+mod cache;
+mod clipboard;
+mod color;
+mod doctor;
+mod error;
+mod export;
+mod import;
+mod locale;
+mod money;
 mod payment;
+mod schema;
+mod store;
+mod template;
+mod tui;
 mod utils;
 
 use anyhow::anyhow;
 
-use crate::payment::{get_config, PaymentManager};
+use crate::{
+    cache::CacheKey,
+    color::ColorBand,
+    error::BalanceError,
+    locale::Locale,
+    money::MoneyFormat,
+    payment::{
+        AmountChange, BalanceReport, Config, DayStrategy, PaymentDetail, PaymentManager,
+        PaymentsExt,
+    },
+    store::{ConfigStore, ConfyStore},
+    template::Template,
+};
 
 #[derive(Parser)]
 struct App {
     #[command(subcommand)]
     command: Commands,
+    /// Error on unknown keys in the config file instead of ignoring them.
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Save changes without asking for confirmation first.
+    #[arg(short, long, global = true)]
+    yes: bool,
+    /// How errors are printed to stderr.
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+    /// Show money and dates the way a region does, such as `de-DE`, instead of as the config sets.
+    /// Flags such as `--date-format` still take precedence.
+    #[arg(long, global = true, value_parser = Locale::parse)]
+    locale: Option<Locale>,
+    /// Only save the config, and back it up, if the command changes it.
+    #[arg(long, global = true)]
+    if_changed: bool,
+}
+
+/// Global settings that change how any command runs.
+#[derive(Debug, Default, Clone, Copy)]
+struct RunOptions {
+    locale: Option<Locale>,
+    if_changed: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    /// A human readable message.
+    Text,
+    /// A JSON object with a stable `code` and a `message`.
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -25,61 +93,664 @@ enum Commands {
     Compute(ComputeArgs),
     /// For adjusting a bill.
     Adjust(AdjustArgs),
+    /// For adding a new bill.
+    Add(AddArgs),
+    /// For removing a bill.
+    Remove(RemoveArgs),
+    /// For showing the details of a bill.
+    Show(ShowArgs),
+    /// For removing bills that share a name with another bill.
+    Dedupe(DedupeArgs),
+    /// For pausing a bill without removing it.
+    Archive(ArchiveArgs),
+    /// For resuming an archived bill.
+    Unarchive(ArchiveArgs),
+    /// For archiving an active bill or resuming an archived one.
+    Toggle(ToggleArgs),
     /// For listing all the bills.
     List(ListArgs),
+    /// For totalling the bills paid each month.
+    Total(TotalArgs),
     /// For editing the bill config.
     Edit(EditArgs),
+    /// For importing bills from a CSV file.
+    Import(ImportArgs),
+    /// For writing the bills out as CSV, in the form `import` reads.
+    Export(ExportArgs),
+    /// For projecting the balance over the coming bill cycles.
+    Forecast(ForecastArgs),
+    /// For comparing monthly outgoings against the configured budget.
+    Budget(BudgetArgs),
+    /// For summarising monthly costs by category.
+    Stats(StatsArgs),
+    /// For printing a JSON Schema of the config, so editors can validate it.
+    Schema(SchemaArgs),
+    /// For browsing and editing bills on an interactive dashboard.
+    Tui(TuiArgs),
+    /// For checking the config for bad data, and optionally fixing it.
+    Doctor(DoctorArgs),
+    /// For showing when each bill is next due.
+    Due(DueArgs),
+    /// For writing the bills due soon to a JSON file, for a notifier to pick up.
+    Remind(RemindArgs),
+    /// For merging the bills from another config file into yours.
+    Merge(MergeArgs),
+    /// For showing the settings `compute` would use and where each came from.
+    Config(ConfigArgs),
 }
 
-#[derive(Args)]
+impl Commands {
+    /// Whether the command holds the config lock from loading the config to storing it. Only
+    /// commands that change the config take it, so reading it is never blocked. The tui locks
+    /// around each save instead, so it doesn't hold the lock while waiting for input.
+    const fn takes_lock(&self) -> bool {
+        match self {
+            Self::Adjust(_)
+            | Self::Add(_)
+            | Self::Remove(_)
+            | Self::Dedupe(_)
+            | Self::Archive(_)
+            | Self::Unarchive(_)
+            | Self::Toggle(_)
+            | Self::Edit(_)
+            | Self::Merge(_) => true,
+            Self::Import(args) => !args.dry_run,
+            Self::Doctor(args) => args.fix,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Money with the configured symbol and grouping.
+    Pretty,
+    /// Bare numbers that are easy for other programs to parse.
+    Plain,
+    /// One JSON object per line, only supported when listing.
+    Ndjson,
+    /// `[[payments]]` tables to paste into a TOML config, only supported when listing.
+    Toml,
+    /// `KEY=value` lines for a shell to `eval`, only supported by compute.
+    Env,
+}
+
+impl OutputFormat {
+    /// Uses the given format, otherwise pretty output for terminals and plain output for pipes.
+    fn resolve(format: Option<Self>, is_terminal: bool) -> Self {
+        format.unwrap_or(if is_terminal {
+            Self::Pretty
+        } else {
+            Self::Plain
+        })
+    }
+
+    fn money(self, money: &MoneyFormat) -> MoneyFormat {
+        match self {
+            Self::Pretty => money.clone(),
+            Self::Plain | Self::Ndjson | Self::Toml | Self::Env => money.plain(),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("starting_balance").required(true).args(["balance", "from_balance_file", "balances", "accounts"])))]
 struct ComputeArgs {
     /// Current balance of your account.
-    balance: Decimal,
+    balance: Option<Decimal>,
+    /// File to read the current balance from, taken from its first line.
+    #[arg(long)]
+    from_balance_file: Option<PathBuf>,
+    /// Show a table of the remaining balance for each of these comma separated starting balances.
+    #[arg(
+        long,
+        visible_alias = "table",
+        value_delimiter = ',',
+        allow_negative_numbers = true,
+        conflicts_with_all = [
+            "explain",
+            "daily",
+            "fail_on_negative",
+            "warn_threshold",
+            "zero_date",
+            "as_percentage_of",
+        ]
+    )]
+    balances: Vec<Decimal>,
+    /// Comma separated `name=amount` account balances, summed to give the starting balance.
+    #[arg(long, value_delimiter = ',', value_parser = parse_account)]
+    accounts: Vec<(String, Decimal)>,
     /// Day your bill cycle resets, normally pay day. Defaults to 18 as that is the author's pay day.
     #[arg(short, long, default_value_t = 18)]
     reset_day: isize,
+    /// Use today as the reset day, moved back to the 28th at the end of the month. Handy just after
+    /// pay day.
+    #[arg(long, conflicts_with = "reset_day")]
+    reset_today: bool,
+    /// Date to compute the balance as of, defaults to today.
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+    /// Use the current UTC date rather than the local date.
+    #[arg(long)]
+    utc: bool,
+    /// Explain which bills are counted against the balance and why.
+    #[arg(long)]
+    explain: bool,
+    /// Also show how many days into the bill cycle today is.
+    #[arg(long)]
+    since_reset: bool,
+    /// How bills due after the end of a short month are handled, overriding the config.
+    #[arg(long, value_enum)]
+    round_day_strategy: Option<DayStrategy>,
+    /// Show how many days the balance lasts at this cycle's average daily bill spend instead.
+    #[arg(long)]
+    relative: bool,
+    /// Also show the remaining balance as a percentage of this amount, such as your take home pay.
+    #[arg(long, allow_negative_numbers = true)]
+    as_percentage_of: Option<Decimal>,
+    /// Show a single line with the balance, bills left and reset day, for status bars.
+    #[arg(
+        long,
+        conflicts_with_all = ["explain", "relative", "since_reset", "daily", "zero_date", "as_percentage_of"]
+    )]
+    compact: bool,
+    /// Don't colour the balance by the config's `color_thresholds`, also set by `NO_COLOR`.
+    #[arg(long)]
+    no_color: bool,
+    /// Also show the date the balance would go negative if no income is paid in.
+    #[arg(long)]
+    zero_date: bool,
+    /// Months ahead to look for the date the balance goes negative.
+    #[arg(long, default_value_t = 12, requires = "zero_date")]
+    zero_date_months: u32,
+    /// Weight bills whose amount changed this cycle by the days at the old and new amounts.
+    #[arg(long)]
+    prorate: bool,
+    /// Keep running, printing the balance again whenever the config file changes, until stopped
+    /// with Ctrl-C.
+    #[arg(long, conflicts_with = "balances")]
+    watch_config: bool,
+    /// Also show the exact balance next to the rounded one shown, noting any rounding.
+    #[arg(long, conflicts_with_all = ["relative", "compact"])]
+    rounding_report: bool,
+    /// Add the bills still to come back on, for a balance they have already been taken from.
+    #[arg(long, conflicts_with_all = ["explain", "relative", "daily", "zero_date"])]
+    add_back: bool,
+    /// Show the balance without taking off the buffer set in the config.
+    #[arg(long)]
+    ignore_buffer: bool,
+    /// Exit with a non-zero status when the remaining balance is negative.
+    #[arg(long)]
+    fail_on_negative: bool,
+    /// Warn when the remaining balance is below this amount.
+    #[arg(long, allow_negative_numbers = true)]
+    warn_threshold: Option<Decimal>,
+    /// Exit with a non-zero status when the remaining balance is below the warning threshold.
+    #[arg(long, requires = "warn_threshold")]
+    fail_on_warn: bool,
+    /// Output format, defaults to pretty in a terminal and plain otherwise.
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["pretty", "plain", "env"])
+            .map(|s| OutputFormat::from_str(&s, true).unwrap())
+    )]
+    format: Option<OutputFormat>,
+    /// Also show how much can safely be spent each day until the cycle resets.
+    #[arg(long)]
+    daily: bool,
+    /// Decimal places to round the daily spend down to. Rounding down means spending the daily
+    /// amount never leaves you short.
+    #[arg(
+        long,
+        default_value = "2",
+        requires = "daily",
+        value_parser = PossibleValuesParser::new(["0", "2"]).map(|s| s.parse::<u32>().unwrap())
+    )]
+    round_to: u32,
+    /// Format for any dates shown, overriding the config. Uses chrono's strftime syntax.
+    #[arg(long)]
+    date_format: Option<String>,
+    /// Always compute the balance, rather than reusing the last result when nothing has changed.
+    #[arg(long)]
+    no_cache: bool,
+}
+
+/// Exit status used by `compute --fail-on-negative`, distinct from the status used for errors.
+const NEGATIVE_BALANCE_STATUS: u8 = 2;
+/// Exit status used by `compute --fail-on-warn`.
+const LOW_BALANCE_STATUS: u8 = 3;
+/// Exit status used by `doctor` when it finds problems it hasn't fixed.
+const PROBLEMS_FOUND_STATUS: u8 = 4;
+
+/// How often `compute --watch-config` checks whether the config has changed.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Currency symbol of the stored config, read before the arguments are parsed so amounts can be
+/// given with it.
+static CURRENCY_SYMBOL: OnceLock<Option<String>> = OnceLock::new();
+
+impl ComputeArgs {
+    fn current_day(&self) -> NaiveDate {
+        self.as_of
+            .unwrap_or_else(|| utils::current_date(chrono::Utc::now(), &chrono::Local, self.utc))
+    }
+
+    fn starting_balance(&self) -> anyhow::Result<Decimal> {
+        if !self.accounts.is_empty() {
+            return sum_accounts(&self.accounts);
+        }
+        match (&self.balance, &self.from_balance_file) {
+            (Some(balance), _) => Ok(*balance),
+            (None, Some(path)) => read_balance_file(path),
+            (None, None) => Err(anyhow!("a balance or balance file is required")),
+        }
+    }
+
+    fn payment_manager(&self, config: &Config) -> anyhow::Result<PaymentManager> {
+        Ok(PaymentManager::new(
+            self.starting_balance()?,
+            self.reset_day(),
+            config.payments.clone(),
+            self.day_strategy(config),
+        ))
+    }
+
+    /// How days past the end of a short month are handled, as given or from the config.
+    fn day_strategy(&self, config: &Config) -> DayStrategy {
+        self.round_day_strategy.unwrap_or(config.round_day_strategy)
+    }
+
+    /// Whether to colour the balance, only done for pretty output to a terminal.
+    fn color(&self, is_terminal: bool) -> bool {
+        OutputFormat::resolve(self.format, is_terminal) == OutputFormat::Pretty
+            && color::enabled(self.no_color, is_terminal)
+    }
+
+    /// Reset day to use, either as given or today's day with `--reset-today`.
+    fn reset_day(&self) -> isize {
+        if self.reset_today {
+            (self.current_day().day() as isize).min(*payment::DAYS_PAID_RANGE.end())
+        } else {
+            self.reset_day
+        }
+    }
+
+    fn below_threshold(&self, balance: Decimal) -> bool {
+        self.warn_threshold.is_some_and(|t| balance < t)
+    }
+
+    fn exit_status(&self, balance: Decimal) -> u8 {
+        if self.fail_on_negative && balance < Decimal::ZERO {
+            NEGATIVE_BALANCE_STATUS
+        } else if self.fail_on_warn && self.below_threshold(balance) {
+            LOW_BALANCE_STATUS
+        } else {
+            0
+        }
+    }
+}
+
+fn parse_account(s: &str) -> Result<(String, Decimal), String> {
+    let (name, amount) = s
+        .split_once('=')
+        .ok_or_else(|| format!("`{s}` isn't of the form name=amount"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("`{s}` is missing an account name"));
+    }
+    let amount: Decimal = amount
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` isn't a Decimal", amount.trim()))?;
+
+    Ok((name.to_owned(), amount))
+}
+
+fn sum_accounts(accounts: &[(String, Decimal)]) -> anyhow::Result<Decimal> {
+    let mut seen = HashSet::new();
+    for (name, _) in accounts {
+        if !seen.insert(name) {
+            return Err(anyhow!("account {name} given more than once"));
+        }
+    }
+
+    Ok(accounts.iter().map(|(_, amount)| amount).sum())
+}
+
+fn read_balance_file(path: &Path) -> anyhow::Result<Decimal> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("couldn't read balance file {}: {e}", path.display()))?;
+    let line = contents.lines().next().unwrap_or_default().trim();
+
+    line.parse()
+        .map_err(|_| anyhow!("`{line}` in {} isn't a Decimal", path.display()))
+}
+
+#[derive(Serialize, Deserialize)]
+struct ComputeOutput {
+    lines: Vec<String>,
+    warnings: Vec<String>,
+    balance: Decimal,
+    /// Line showing the balance and the band to colour it in.
+    #[serde(default)]
+    highlight: Option<(usize, ColorBand)>,
+}
+
+/// Key the output of `compute` is cached under, or `None` if it isn't cached.
+fn compute_cache_key(
+    args: &ComputeArgs,
+    locale: Option<Locale>,
+    store: &impl ConfigStore,
+    is_terminal: bool,
+) -> anyhow::Result<Option<CacheKey>> {
+    if args.no_cache || !args.balances.is_empty() {
+        return Ok(None);
+    }
+    let Some(config_modified) = store.modified()? else {
+        return Ok(None);
+    };
+    Ok(Some(CacheKey {
+        config_modified,
+        date: args.current_day(),
+        balance: args.starting_balance()?,
+        options: format!(
+            "{args:?} {:?} {locale:?} strict: {}",
+            OutputFormat::resolve(args.format, is_terminal),
+            store.strict()
+        ),
+    }))
+}
+
+fn print_compute(args: &ComputeArgs, output: &ComputeOutput, color: bool) -> u8 {
+    for (i, line) in output.lines.iter().enumerate() {
+        match output.highlight {
+            Some((line_index, band)) if color && line_index == i => {
+                println!("{}", band.paint(line))
+            }
+            _ => println!("{line}"),
+        }
+    }
+    for warning in &output.warnings {
+        eprintln!("{warning}");
+    }
+    args.exit_status(output.balance)
+}
+
+fn compute_balance(
+    args: &ComputeArgs,
+    config: &Config,
+    money: &MoneyFormat,
+) -> anyhow::Result<ComputeOutput> {
+    let buffer = if args.ignore_buffer {
+        Decimal::ZERO
+    } else {
+        config.buffer
+    };
+    let payment_manager = args
+        .payment_manager(config)?
+        .with_buffer(buffer)
+        .with_prorate(args.prorate);
+    let current_day = args.current_day();
+    let date_format = args.date_format.as_deref().unwrap_or(&config.date_format);
+
+    let report = payment_manager.report(&current_day);
+    let mut lines = vec![];
+    if args.explain {
+        lines.extend(explain_report(&report, money, date_format)?);
+    }
+    let balance = if args.add_back {
+        payment_manager.balance_before_bills(&current_day)
+    } else {
+        report.remaining
+    };
+    let band = config.color_thresholds.map(|t| t.band(balance));
+    let mut highlight = None;
+    if args.relative {
+        let runway = payment_manager.runway_days(&current_day).map_or_else(
+            || "No bills to spend on, the balance lasts indefinitely".to_owned(),
+            |days| format!("~{days} days of runway"),
+        );
+        lines.push(runway);
+    } else {
+        highlight = band.map(|b| (lines.len(), b));
+        lines.push(money.format(balance));
+    }
+    if args.rounding_report {
+        lines.extend(rounding_report(balance, money));
+    }
+    if let Some(income) = args.as_percentage_of {
+        if income.is_zero() {
+            return Err(anyhow!("can't show the balance as a percentage of zero"));
+        }
+        let percent = (balance / income * Decimal::ONE_HUNDRED)
+            .round_dp_with_strategy(1, RoundingStrategy::MidpointAwayFromZero);
+        lines.push(format!("{percent}% of {}", money.format(income)));
+    }
+    if !buffer.is_zero() {
+        lines.push(format!(
+            "Keeping {} aside from {}.",
+            money.format(buffer),
+            money.format(balance + buffer)
+        ));
+    }
+    if args.since_reset {
+        lines.push(elapsed_line(&report));
+    }
+    if args.zero_date {
+        let line = match payment_manager.zero_date(&current_day, args.zero_date_months) {
+            Some(date) => format!(
+                "Without income the balance goes negative on {}.",
+                utils::format_date(&date, date_format)?
+            ),
+            None => format!(
+                "Without income the balance never goes negative within {} month{}.",
+                args.zero_date_months,
+                if args.zero_date_months == 1 { "" } else { "s" }
+            ),
+        };
+        lines.push(line);
+    }
+    if args.daily {
+        let daily = payment_manager
+            .safe_daily_spend(&current_day)
+            .round_dp_with_strategy(args.round_to, RoundingStrategy::ToZero);
+        lines.push(format!("Safe to spend per day: {}", money.format(daily)));
+    }
+
+    let mut warnings = vec![];
+    if let Some(threshold) = args
+        .warn_threshold
+        .filter(|_| args.below_threshold(balance))
+    {
+        warnings.push(format!(
+            "warning: balance is below {}",
+            money.format(threshold)
+        ));
+    }
+
+    let lines = if args.format == Some(OutputFormat::Env) {
+        highlight = None;
+        let date = utils::format_date(&current_day, date_format)?;
+        vec![
+            env_line("BALANCE_REMAINING", &money.format(balance)),
+            env_line("BALANCE_RESET_DAY", &args.reset_day().to_string()),
+            env_line("BALANCE_DATE", &date),
+        ]
+    } else if args.compact {
+        highlight = band.map(|b| (0, b));
+        let left = report.details.iter().filter(|d| d.leftover).count();
+        vec![format!(
+            "{} ({left} bill{} left, reset {})",
+            money.format(balance),
+            if left == 1 { "" } else { "s" },
+            utils::ordinal(args.reset_day())
+        )]
+    } else {
+        lines
+    };
+
+    Ok(ComputeOutput {
+        lines,
+        warnings,
+        balance,
+        highlight,
+    })
+}
+
+/// The exact balance and the rounded figure shown for it, noting the difference if rounding
+/// changed it.
+fn rounding_report(balance: Decimal, money: &MoneyFormat) -> Vec<String> {
+    let rounded = money.rounding.round(balance);
+    let note = if rounded == balance {
+        "no rounding needed".to_owned()
+    } else {
+        format!("rounded by {}", rounded - balance)
+    };
+    vec![
+        format!("Exact balance: {balance}"),
+        format!("Shown as: {} ({note})", money.format(balance)),
+    ]
+}
+
+/// A `KEY=value` line a shell can `eval`, with the value single quoted unless it only contains
+/// characters that are safe bare.
+fn env_line(key: &str, value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/' | ':');
+    if !value.is_empty() && value.chars().all(safe) {
+        format!("{key}={value}")
+    } else {
+        format!("{key}='{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+fn balance_table(args: &ComputeArgs, config: &Config, money: &MoneyFormat) -> Vec<String> {
+    let current_day = args.current_day();
+
+    args.balances
+        .iter()
+        .map(|&balance| {
+            let remaining = PaymentManager::new(
+                balance,
+                args.reset_day(),
+                config.payments.clone(),
+                args.day_strategy(config),
+            )
+            .remaining_balance(&current_day);
+            format!("{} -> {}", money.format(balance), money.format(remaining))
+        })
+        .collect()
 }
 
-fn compute_balance(args: &ComputeArgs, payments: Payments) -> Decimal {
-    let ComputeArgs { balance, reset_day } = args;
+fn elapsed_line(report: &BalanceReport) -> String {
+    format!(
+        "Day {} of {} in the current cycle.",
+        report.rebased_day + 1,
+        report.cycle_length
+    )
+}
 
-    let payment_manager = PaymentManager::new(*balance, *reset_day, payments);
+fn explain_report(
+    report: &BalanceReport,
+    money: &MoneyFormat,
+    date_format: &str,
+) -> anyhow::Result<Vec<String>> {
+    let mut lines = vec![
+        format!(
+            "Today is day {} of the bill cycle, counting the reset day as day 0.",
+            report.rebased_day
+        ),
+        format!(
+            "The cycle next resets on {}.",
+            utils::format_date(&report.next_reset, date_format)?
+        ),
+    ];
 
-    let current_day = chrono::Utc::now().date_naive();
+    for detail in &report.details {
+        let PaymentDetail {
+            payment,
+            amount,
+            rebased_day,
+            today,
+            leftover,
+        } = detail;
+        let (paid, today) = payment.reset_day.map_or_else(
+            || {
+                (
+                    format!("{} is paid on day {rebased_day}", payment.name),
+                    "today".to_owned(),
+                )
+            },
+            |reset_day| {
+                (
+                    format!(
+                        "{} is paid on day {rebased_day} of its own cycle from day {reset_day}",
+                        payment.name
+                    ),
+                    format!("today, day {today} of that cycle"),
+                )
+            },
+        );
+        let line = if *leftover {
+            format!(
+                "{paid}, which is after {today}, so {} is still to come out.",
+                money.format((*amount).into())
+            )
+        } else {
+            format!("{paid}, which is not after {today}, so it has already come out.")
+        };
+        lines.push(line);
+    }
 
-    payment_manager.remaining_balance(&current_day)
+    Ok(lines)
 }
 
 #[derive(Args)]
+#[command(group(ArgGroup::new("target").required(true).args(["name", "category"])))]
+#[command(group(ArgGroup::new("new_amount").args(["amount", "by_percent"])))]
 struct AdjustArgs {
     /// Bill item to adjust.
-    name: String,
+    name: Option<String>,
+    /// Adjust every bill in this category instead of a single bill.
+    #[arg(short, long)]
+    category: Option<String>,
     /// New bill amount.
     #[arg(short, long, value_parser = amount_validation)]
     amount: Option<Decimal>,
+    /// Percentage to change the bill amount by, may be negative.
+    #[arg(
+        long,
+        conflicts_with = "amount",
+        allow_negative_numbers = true,
+        value_parser = percent_validation
+    )]
+    by_percent: Option<Decimal>,
     /// New day that the bill is paid on.
     #[arg(short, long, value_parser = days_paid_in_range)]
     day_paid: Option<isize>,
+    /// Store the adjusted amount with exactly two decimal places, so `12.5` becomes `12.50`.
+    #[arg(long)]
+    normalize: bool,
+    /// Date the new amount takes effect, kept so `compute --prorate` can weight the old and new
+    /// amounts.
+    #[arg(long, requires = "new_amount")]
+    effective: Option<NaiveDate>,
+    /// Add the bill if it doesn't exist yet, which needs both `--amount` and `--day-paid`.
+    #[arg(long, conflicts_with = "category")]
+    create: bool,
 }
 
-const DAYS_PAID_RANGE: RangeInclusive<isize> = 1..=28;
-
 fn days_paid_in_range(s: &str) -> Result<isize, String> {
     let days_paid: isize = s.parse().map_err(|_| format!("`{s}` isn't an integer"))?;
 
-    if DAYS_PAID_RANGE.contains(&days_paid) {
-        Ok(days_paid)
-    } else {
-        Err(format!(
-            "days paid not in range {}-{}",
-            DAYS_PAID_RANGE.start(),
-            DAYS_PAID_RANGE.end()
-        ))
-    }
+    payment::validate_day_paid(days_paid).map_err(|e| e.to_string())
 }
 
 fn amount_validation(s: &str) -> Result<Decimal, String> {
-    let amount: Decimal = s.parse().map_err(|_| format!("`{s}` isn't a Decimal"))?;
+    let configured = CURRENCY_SYMBOL.get().and_then(Option::as_deref);
+    let amount: Decimal = money::strip_currency_symbol(s, configured)
+        .parse()
+        .map_err(|_| format!("`{s}` isn't a Decimal"))?;
 
     if amount >= Decimal::new(0, 2) {
         Ok(amount)
@@ -88,20 +759,204 @@ fn amount_validation(s: &str) -> Result<Decimal, String> {
     }
 }
 
+fn percent_validation(s: &str) -> Result<Decimal, String> {
+    let percent: Decimal = s.parse().map_err(|_| format!("`{s}` isn't a Decimal"))?;
+
+    if percent >= Decimal::ONE_HUNDRED.neg() {
+        Ok(percent)
+    } else {
+        Err("percentage not greater than or equal to -100".to_string())
+    }
+}
+
+fn apply_adjustment(args: &AdjustArgs, payment: &mut Payment) {
+    let previous = payment.amount;
+    if let Some(a) = args.amount {
+        payment.amount = a;
+    }
+    if let Some(p) = args.by_percent {
+        payment.amount = utils::apply_percent(payment.amount, p);
+    }
+    if args.normalize {
+        payment.amount = payment::normalize_amount(payment.amount);
+    }
+    if let Some(effective) = args.effective {
+        payment.change = Some(AmountChange {
+            previous,
+            effective,
+        });
+    }
+    if let Some(d) = args.day_paid {
+        payment.day_paid = d;
+    }
+}
+
 fn adjust_entry(args: &AdjustArgs, mut payments: Payments) -> anyhow::Result<Payments> {
-    for payment in payments.iter_mut() {
-        if payment.name != args.name {
-            continue;
+    if let Some(category) = &args.category {
+        let mut adjusted = payments
+            .iter_mut()
+            .filter(|p| p.category.as_ref() == Some(category))
+            .peekable();
+        if adjusted.peek().is_none() {
+            return Err(
+                BalanceError::NotFound(format!("no bills found in category {category}")).into(),
+            );
         }
-        if let Some(a) = args.amount {
-            payment.amount = a;
-        }
-        if let Some(d) = args.day_paid {
-            payment.day_paid = d;
+        adjusted.for_each(|p| apply_adjustment(args, p));
+        return Ok(payments);
+    }
+
+    let name = args.name.as_deref().unwrap_or_default();
+    if args.create && payments.position_of(name).is_none() {
+        let (Some(amount), Some(day_paid)) = (args.amount, args.day_paid) else {
+            return Err(BalanceError::Invalid(format!(
+                "{name} doesn't exist, so --amount and --day-paid are needed to create it"
+            ))
+            .into());
+        };
+        let mut payment = Payment::builder()
+            .name(name)
+            .amount(amount)
+            .day_paid(day_paid)
+            .build()?;
+        if args.normalize {
+            payment.amount = payment::normalize_amount(payment.amount);
         }
+        payments.push(payment);
         return Ok(payments);
     }
-    Err(anyhow!("{} not found", args.name))
+
+    let index = payments.find(name)?;
+    apply_adjustment(args, &mut payments[index]);
+    Ok(payments)
+}
+
+#[derive(Args)]
+struct AddArgs {
+    /// Name of the new bill.
+    name: String,
+    /// Amount the bill will be.
+    #[arg(value_parser = amount_validation)]
+    amount: Decimal,
+    /// Day of the month the bill is paid on.
+    #[arg(value_parser = days_paid_in_range)]
+    day_paid: isize,
+    /// Category to group the bill under.
+    #[arg(short, long)]
+    category: Option<String>,
+}
+
+fn add_entry(args: &AddArgs, mut payments: Payments) -> anyhow::Result<Payments> {
+    if payments.iter().any(|p| p.name == args.name) {
+        return Err(BalanceError::AlreadyExists(format!("{} already exists", args.name)).into());
+    }
+    let payment = Payment::builder()
+        .name(&args.name)
+        .amount(args.amount)
+        .day_paid(args.day_paid)
+        .category(args.category.as_ref())
+        .build()?;
+    payments.push(payment);
+    Ok(payments)
+}
+
+#[derive(Args)]
+struct RemoveArgs {
+    /// Bill item to remove.
+    name: String,
+}
+
+fn remove_entry(args: &RemoveArgs, mut payments: Payments) -> anyhow::Result<Payments> {
+    let index = payments.find(&args.name)?;
+    payments.remove(index);
+    Ok(payments)
+}
+
+#[derive(Args)]
+struct ArchiveArgs {
+    /// Bill item to archive or unarchive.
+    name: String,
+}
+
+fn set_archived(
+    args: &ArchiveArgs,
+    archived: bool,
+    mut payments: Payments,
+) -> anyhow::Result<Payments> {
+    let index = payments.find(&args.name)?;
+    payments[index].archived = archived;
+    Ok(payments)
+}
+
+#[derive(Args)]
+struct ToggleArgs {
+    /// Bill item to toggle.
+    name: String,
+}
+
+/// Flips whether the named bill is archived, returning its new state.
+fn toggle_archived(args: &ToggleArgs, mut payments: Payments) -> anyhow::Result<(Payments, bool)> {
+    let index = payments.find(&args.name)?;
+    let payment = &mut payments[index];
+    payment.archived = !payment.archived;
+    let archived = payment.archived;
+    Ok((payments, archived))
+}
+
+#[derive(Args)]
+struct ShowArgs {
+    /// Bill item to show.
+    name: String,
+}
+
+fn show_entry(args: &ShowArgs, payments: &Payments, money: &MoneyFormat) -> anyhow::Result<String> {
+    let index = payments.find(&args.name)?;
+    Ok(payments[index].display(money).to_string())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Keep {
+    First,
+    Last,
+}
+
+#[derive(Args)]
+struct DedupeArgs {
+    /// Which of the bills sharing a name to keep.
+    #[arg(short, long, value_enum, default_value_t = Keep::First)]
+    keep: Keep,
+}
+
+fn dedupe_entries(args: &DedupeArgs, mut payments: Payments) -> (Payments, usize) {
+    let before = payments.len();
+    if args.keep == Keep::Last {
+        payments.reverse();
+    }
+
+    let mut seen = HashSet::new();
+    payments.retain(|p| seen.insert(p.name.clone()));
+
+    if args.keep == Keep::Last {
+        payments.reverse();
+    }
+    let removed = before - payments.len();
+    (payments, removed)
+}
+
+/// Filters shared by the commands that read bills.
+#[derive(Args, Debug, Default, Clone)]
+struct NameFilter {
+    /// Only include bills whose name contains this text, ignoring case.
+    #[arg(long)]
+    name_contains: Option<String>,
+}
+
+impl NameFilter {
+    fn matches(&self, payment: &Payment) -> bool {
+        self.name_contains
+            .as_ref()
+            .is_none_or(|text| payment.name.to_lowercase().contains(&text.to_lowercase()))
+    }
 }
 
 #[derive(Args)]
@@ -112,51 +967,4250 @@ struct ListArgs {
     /// Whether to include the day the bill is paid in the output.
     #[arg(short, long)]
     day_paid: bool,
+    /// Only list bills in this category.
+    #[arg(short, long)]
+    category: Option<String>,
+    /// Whether to finish the output with the total of the listed bills.
+    #[arg(short, long)]
+    summary: bool,
+    /// Whether to group the bills by the day they are paid, with a subtotal for each day.
+    #[arg(short, long)]
+    group_by_day: bool,
+    /// Only list bills with at least this amount.
+    #[arg(long, value_parser = amount_validation)]
+    min_amount: Option<Decimal>,
+    /// Only list bills with at most this amount.
+    #[arg(long, value_parser = amount_validation)]
+    max_amount: Option<Decimal>,
+    /// Output format, defaults to pretty in a terminal and plain otherwise.
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(["pretty", "plain", "ndjson", "toml"])
+            .map(|s| OutputFormat::from_str(&s, true).unwrap())
+    )]
+    format: Option<OutputFormat>,
+    /// Whether to include archived bills.
+    #[arg(long)]
+    include_archived: bool,
+    /// Whether to include the next date each bill is due, counting today.
+    #[arg(long, conflicts_with = "group_by_day")]
+    due_dates: bool,
+    /// List at most this many bills.
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Skip this many bills before listing, for paging with `--limit`.
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+    /// Whether to include bills with a zero amount, the default.
+    #[arg(long, overrides_with = "exclude_zero")]
+    include_zero: bool,
+    /// Whether to leave out bills with a zero amount.
+    #[arg(long, overrides_with = "include_zero")]
+    exclude_zero: bool,
+    /// Order to list the bills in.
+    #[arg(long, value_enum, default_value_t = ListSort::Name)]
+    sort: ListSort,
+    /// Day your bill cycle resets, used when sorting by day.
+    #[arg(short, long, default_value_t = 18)]
+    reset_day: isize,
+    /// Format each bill with placeholders `{name}`, `{amount}`, `{day}` and `{category}`, in place
+    /// of the amount and day paid flags.
+    #[arg(long, value_parser = Template::parse)]
+    template: Option<Template>,
+    /// Print tab separated columns for pasting into a spreadsheet, with the amount and day paid
+    /// columns chosen by their flags.
+    #[arg(long, conflicts_with_all = ["template", "group_by_day", "summary", "format"])]
+    tsv: bool,
+    /// Print just the bill names, one per line, for piping into other commands. The amount and day
+    /// paid flags are ignored.
+    #[arg(
+        long,
+        conflicts_with_all = ["template", "group_by_day", "summary", "format", "tsv", "due_dates"]
+    )]
+    only_name: bool,
+    #[command(flatten)]
+    filter: NameFilter,
 }
 
-fn list_payments(args: &ListArgs, payments: &mut Payments) {
-    payments.sort();
-    let ListArgs { amount, day_paid } = args;
-    for payment in payments {
-        let output = match (amount, day_paid) {
-            (true, true) => {
-                format!(
-                    "{} £{}, day paid: {}",
-                    payment.name, payment.amount, payment.day_paid
-                )
-            }
-            (true, false) => format!("{} £{}", payment.name, payment.amount),
-            (false, true) => format!("{}, day_paid: {}", payment.name, payment.day_paid),
-            (false, false) => payment.name.clone(),
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ListSort {
+    /// Alphabetically by name.
+    Name,
+    /// By the order the bills come out in the current bill cycle, counting from the reset day.
+    Day,
+    /// By category name, with uncategorised bills last, then by name.
+    Category,
+}
+
+impl ListArgs {
+    fn includes(&self, payment: &Payment, today: &NaiveDate) -> bool {
+        (self.include_archived || payment.is_active(today))
+            && (self.category.is_none() || payment.category == self.category)
+            && self.min_amount.is_none_or(|min| payment.amount >= min)
+            && self.max_amount.is_none_or(|max| payment.amount <= max)
+            && !(self.exclude_zero && payment.amount.is_zero())
+            && self.filter.matches(payment)
+    }
+}
+
+fn format_payment(args: &ListArgs, payment: &Payment, money: &MoneyFormat) -> String {
+    if let Some(template) = &args.template {
+        return template.render(payment, money);
+    }
+    match (args.amount, args.day_paid) {
+        (true, true) => {
+            format!(
+                "{} {}, day paid: {}",
+                payment.name,
+                money.format(payment.amount),
+                payment.days_paid_text()
+            )
+        }
+        (true, false) => format!("{} {}", payment.name, money.format(payment.amount)),
+        (false, true) => format!("{}, day_paid: {}", payment.name, payment.days_paid_text()),
+        (false, false) => payment.name.clone(),
+    }
+}
+
+/// The payment as a tab separated row, with the columns chosen by the list flags.
+fn payment_tsv(
+    args: &ListArgs,
+    payment: &Payment,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+    date_format: &str,
+) -> anyhow::Result<String> {
+    let mut columns = vec![payment.name.clone()];
+    if args.amount {
+        columns.push(money.plain().format(payment.amount));
+    }
+    if args.day_paid {
+        let days: Vec<String> = payment
+            .days_paid()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        columns.push(days.join(","));
+    }
+    if args.due_dates {
+        columns.push(utils::format_date(&payment.next_due(today), date_format)?);
+    }
+    Ok(columns.join("\t"))
+}
+
+/// The payment as a single line JSON object, with the fields chosen by the list flags.
+fn payment_json(
+    args: &ListArgs,
+    payment: &Payment,
+    today: &NaiveDate,
+    date_format: &str,
+) -> anyhow::Result<String> {
+    let mut object = serde_json::Map::new();
+    object.insert("name".to_owned(), payment.name.clone().into());
+    if args.amount {
+        object.insert("amount".to_owned(), payment.amount.to_string().into());
+    }
+    if args.day_paid {
+        let day_paid = match payment.days_paid().as_slice() {
+            [day] => (*day).into(),
+            days => days.into(),
         };
+        object.insert("day_paid".to_owned(), day_paid);
+    }
+    if args.due_dates {
+        let due = utils::format_date(&payment.next_due(today), date_format)?;
+        object.insert("due".to_owned(), due.into());
+    }
+    Ok(serde_json::Value::Object(object).to_string())
+}
+
+/// The bills under the days they are paid this month, then those `day_strategy` skips this month.
+fn group_by_day(
+    args: &ListArgs,
+    listed: &[&Payment],
+    today: &NaiveDate,
+    money: &MoneyFormat,
+    day_strategy: DayStrategy,
+) -> Vec<String> {
+    let days_in_month = utils::days_in_month(today);
+
+    let mut days: BTreeMap<isize, Vec<&Payment>> = BTreeMap::new();
+    let mut skipped = vec![];
+    for payment in listed {
+        let paid: Vec<isize> = payment
+            .days_paid()
+            .into_iter()
+            .filter_map(|day| day_strategy.day_in(day, days_in_month))
+            .collect();
+        if paid.is_empty() {
+            skipped.push(payment);
+        }
+        for day in paid {
+            days.entry(day).or_default().push(payment);
+        }
+    }
+
+    let mut lines = vec![];
+    for (day, payments) in days {
+        let subtotal: Decimal = payments.iter().map(|p| p.amount).sum();
+        lines.push(format!("Day {day}: {}", money.format(subtotal)));
+        lines.extend(
+            payments
+                .iter()
+                .map(|p| format!("  {}", format_payment(args, p, money))),
+        );
+    }
+    if !skipped.is_empty() {
+        lines.push("Not paid this month:".to_owned());
+        lines.extend(
+            skipped
+                .iter()
+                .map(|p| format!("  {}", format_payment(args, p, money))),
+        );
+    }
+    lines
+}
+
+fn list_payments(
+    args: &ListArgs,
+    payments: &mut Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+    date_format: &str,
+    day_strategy: DayStrategy,
+) -> anyhow::Result<Vec<String>> {
+    if let (Some(min), Some(max)) = (args.min_amount, args.max_amount) {
+        if min > max {
+            return Err(anyhow!("min amount {min} is greater than max amount {max}"));
+        }
+    }
+
+    payments.sort();
+
+    let days_in_month = utils::days_in_month(today);
+    match args.sort {
+        ListSort::Name => {}
+        // Bills not paid this month go last.
+        ListSort::Day => payments.sort_by_key(|p| {
+            p.days_paid()
+                .into_iter()
+                .filter_map(|day| day_strategy.day_in(day, days_in_month))
+                .map(|day| utils::modulo(day - args.reset_day, days_in_month))
+                .min()
+                .unwrap_or(isize::MAX)
+        }),
+        ListSort::Category => payments.sort_by(|a, b| {
+            (a.category.is_none(), &a.category).cmp(&(b.category.is_none(), &b.category))
+        }),
+    }
+
+    let listed: Vec<&Payment> = payments
+        .iter()
+        .filter(|p| args.includes(p, today))
+        .skip(args.offset)
+        .take(args.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    if args.only_name {
+        return Ok(listed.iter().map(|p| p.name.clone()).collect());
+    }
+
+    if args.format == Some(OutputFormat::Ndjson) {
+        if args.group_by_day || args.summary {
+            return Err(anyhow!(
+                "ndjson output can't be combined with --group-by-day or --summary"
+            ));
+        }
+        return listed
+            .iter()
+            .map(|p| payment_json(args, p, today, date_format))
+            .collect();
+    }
+
+    if args.format == Some(OutputFormat::Toml) {
+        if args.group_by_day || args.summary {
+            return Err(anyhow!(
+                "toml output can't be combined with --group-by-day or --summary"
+            ));
+        }
+        let toml = export::payments_toml(&listed)?;
+        return Ok(toml.lines().map(str::to_owned).collect());
+    }
+
+    if args.tsv {
+        return listed
+            .iter()
+            .map(|p| payment_tsv(args, p, today, money, date_format))
+            .collect();
+    }
+
+    let mut lines: Vec<String> = if args.group_by_day {
+        group_by_day(args, &listed, today, money, day_strategy)
+    } else if args.due_dates {
+        listed
+            .iter()
+            .map(|p| {
+                let due = utils::format_date(&p.next_due(today), date_format)?;
+                Ok(format!("{}, due: {due}", format_payment(args, p, money)))
+            })
+            .collect::<anyhow::Result<_>>()?
+    } else {
+        listed
+            .iter()
+            .map(|p| format_payment(args, p, money))
+            .collect()
+    };
+
+    if args.summary {
+        let total: Decimal = listed.iter().map(|p| p.monthly_cost()).sum();
+        lines.push(format!("Total: {}", money.format(total)));
+    }
+
+    Ok(lines)
+}
+
+#[derive(Args)]
+struct TotalArgs {
+    /// Only total bills in this category.
+    #[arg(short, long)]
+    category: Option<String>,
+    /// Also print the exact sum, with every decimal place the amounts were written with.
+    #[arg(long)]
+    exact: bool,
+    /// Only total the bills still to be paid this cycle, as `compute` takes off the balance.
+    #[arg(long)]
+    future_only: bool,
+    /// Day your bill cycle resets, used with `--future-only`.
+    #[arg(short, long, default_value_t = 18)]
+    reset_day: isize,
+    #[command(flatten)]
+    filter: NameFilter,
+}
+
+fn total_payments(
+    args: &TotalArgs,
+    payments: &Payments,
+    today: &NaiveDate,
+    day_strategy: DayStrategy,
+) -> Decimal {
+    let included = payments
+        .retain_active(today)
+        .into_iter()
+        .filter(|p| args.category.is_none() || p.category == args.category)
+        .filter(|p| args.filter.matches(p));
 
-        println!("{output}");
+    if args.future_only {
+        let payments = included.cloned().collect();
+        return -PaymentManager::new(Decimal::ZERO, args.reset_day, payments, day_strategy)
+            .remaining_balance(today);
     }
+    included.map(Payment::monthly_cost).sum()
+}
+
+fn total_lines(
+    args: &TotalArgs,
+    payments: &Payments,
+    today: &NaiveDate,
+    day_strategy: DayStrategy,
+    money: &MoneyFormat,
+) -> Vec<String> {
+    let total = total_payments(args, payments, today, day_strategy);
+    if !args.exact {
+        return vec![money.format(total)];
+    }
+    vec![
+        format!("Exact total: {total} ({} decimal places)", total.scale()),
+        format!("Shown as: {}", money.format(total)),
+    ]
 }
 
 #[derive(Args)]
 struct EditArgs {}
 
-fn main() -> anyhow::Result<()> {
-    let args = App::parse();
+#[derive(Args)]
+struct SchemaArgs {}
 
-    let mut config = get_config()?;
+#[derive(Args)]
+struct DoctorArgs {
+    /// Check for amounts with more than two decimal places. All checks run if none are chosen.
+    #[arg(long)]
+    precision_check: bool,
+    /// Fix the problems found and save the config.
+    #[arg(long)]
+    fix: bool,
+}
 
-    match &args.command {
-        Commands::Compute(args) => {
-            let balance = compute_balance(args, config.payments);
-            println!("£{}", balance);
-            Ok(())
+impl DoctorArgs {
+    fn rules(&self) -> Vec<doctor::Rule> {
+        if self.precision_check {
+            vec![doctor::Rule::Precision]
+        } else {
+            doctor::Rule::ALL.to_vec()
         }
-        Commands::Adjust(args) => {
-            let payments = adjust_entry(args, config.payments)?;
-            config.payments = payments;
-            store_config(&config)
-        }
-        Commands::List(args) => {
-            list_payments(args, &mut config.payments);
-            Ok(())
+    }
+}
+
+#[derive(Args)]
+struct TuiArgs {
+    /// Day your bill cycle resets, used for the remaining balance.
+    #[arg(short, long, default_value_t = 18)]
+    reset_day: isize,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("balance_taken").args(["start_balance", "current_balance"])))]
+struct ForecastArgs {
+    /// Current balance of your account.
+    balance: Decimal,
+    /// The balance was taken when the cycle reset, before any of this cycle's bills were paid.
+    #[arg(long)]
+    start_balance: bool,
+    /// The balance was taken today, after the bills already due this cycle were paid. The default.
+    #[arg(long)]
+    current_balance: bool,
+    /// Day your bill cycle resets, normally pay day.
+    #[arg(short, long, default_value_t = 18)]
+    reset_day: isize,
+    /// Income paid in at the start of each cycle.
+    #[arg(short, long, value_parser = amount_validation)]
+    income: Option<Decimal>,
+    /// Number of cycles after the current one to project.
+    #[arg(short, long, default_value_t = 3)]
+    cycles: usize,
+    /// Date to start the forecast from, defaults to today.
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+    /// Show a row for each month with its income, bills and end balance.
+    #[arg(long, value_enum)]
+    group_by: Option<ForecastGroup>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ForecastGroup {
+    Month,
+}
+
+fn forecast_balances(
+    args: &ForecastArgs,
+    payments: Payments,
+    day_strategy: DayStrategy,
+    money: &MoneyFormat,
+) -> Vec<String> {
+    let mut payment_manager =
+        PaymentManager::new(args.balance, args.reset_day, payments, day_strategy)
+            .with_balance_at_cycle_start(args.start_balance);
+    if let Some(income) = args.income {
+        payment_manager = payment_manager.with_income(income);
+    }
+
+    let current_day = args
+        .as_of
+        .unwrap_or_else(|| utils::current_date(chrono::Utc::now(), &chrono::Local, false));
+
+    if args.group_by == Some(ForecastGroup::Month) {
+        return payment_manager
+            .monthly_forecast(&current_day, args.cycles)
+            .iter()
+            .map(|row| {
+                format!(
+                    "{}: income {}, bills {}, end balance {}",
+                    row.start.format("%b %Y"),
+                    money.format(row.income),
+                    money.format(row.bills),
+                    money.format(row.balance)
+                )
+            })
+            .collect();
+    }
+
+    payment_manager
+        .projection(&current_day, args.cycles)
+        .iter()
+        .enumerate()
+        .map(|(cycle, balance)| match cycle {
+            0 => format!("This cycle: {}", money.format(*balance)),
+            n => format!("Cycle +{n}: {}", money.format(*balance)),
+        })
+        .collect()
+}
+
+#[derive(Args)]
+struct DueArgs {
+    /// Group bills into this week, next week and later, each in the order they are due.
+    #[arg(long)]
+    sort_by_due_date: bool,
+    /// Date to show bills due from, defaults to today.
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+}
+
+fn due_report(
+    args: &DueArgs,
+    payments: &Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+    date_format: &str,
+) -> anyhow::Result<Vec<String>> {
+    let mut due: Vec<(NaiveDate, &Payment)> = payments
+        .retain_active(today)
+        .into_iter()
+        .map(|p| (p.next_due(today), p))
+        .collect();
+    due.sort_by_key(|(_, p)| *p);
+    let line = |(date, p): &(NaiveDate, &Payment)| -> anyhow::Result<String> {
+        let weekend = if utils::is_weekend(date) {
+            " (weekend)"
+        } else {
+            ""
+        };
+        Ok(format!(
+            "{} {}, due: {}{weekend}",
+            p.name,
+            money.format(p.share()),
+            utils::format_date(date, date_format)?
+        ))
+    };
+
+    if !args.sort_by_due_date {
+        return due.iter().map(line).collect();
+    }
+
+    due.sort_by_key(|(date, _)| *date);
+    let mut lines = vec![];
+    for (label, weeks) in [
+        ("This week", 0..1),
+        ("Next week", 1..2),
+        ("Later", 2..i64::MAX),
+    ] {
+        let bucket: Vec<_> = due
+            .iter()
+            .filter(|(date, _)| weeks.contains(&utils::weeks_between(today, date)))
+            .collect();
+        if bucket.is_empty() {
+            continue;
+        }
+        lines.push(format!("{label}:"));
+        for entry in bucket {
+            lines.push(format!("  {}", line(entry)?));
+        }
+    }
+    Ok(lines)
+}
+
+#[derive(Args)]
+struct RemindArgs {
+    /// File to write the reminders to, replacing anything already in it.
+    path: PathBuf,
+    /// Remind about bills due within this many days, counting today as day 0.
+    #[arg(short, long, default_value_t = 3)]
+    within: u32,
+    /// Date to look for bills due from, defaults to today.
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+}
+
+/// Bills due within the reminder window, soonest first, as a JSON array of objects with the
+/// name, amount and ISO 8601 due date of each.
+fn reminders(args: &RemindArgs, payments: &Payments, today: &NaiveDate) -> serde_json::Value {
+    let last_day = *today + chrono::Duration::days(args.within.into());
+    let mut due: Vec<(NaiveDate, &Payment)> = payments
+        .retain_active(today)
+        .into_iter()
+        .map(|p| (p.next_due(today), p))
+        .filter(|(date, _)| *date <= last_day)
+        .collect();
+    due.sort_by_key(|(date, p)| (*date, *p));
+
+    due.iter()
+        .map(|(date, p)| {
+            serde_json::json!({
+                "name": p.name,
+                "amount": p.share().to_string(),
+                "due": date.format(utils::DEFAULT_DATE_FORMAT).to_string(),
+            })
+        })
+        .collect()
+}
+
+fn write_reminders(
+    args: &RemindArgs,
+    payments: &Payments,
+    today: &NaiveDate,
+) -> anyhow::Result<()> {
+    let contents = serde_json::to_string_pretty(&reminders(args, payments, today))?;
+    std::fs::write(&args.path, contents + "\n")
+        .map_err(|e| anyhow!("couldn't write reminders to {}: {e}", args.path.display()))
+}
+
+/// The same overrides `compute` takes, so their effect can be checked.
+#[derive(Args)]
+struct ConfigArgs {
+    /// Day your bill cycle resets, normally pay day.
+    #[arg(short, long)]
+    reset_day: Option<isize>,
+    /// How bills due after the end of a short month are handled, overriding the config.
+    #[arg(long, value_enum)]
+    round_day_strategy: Option<DayStrategy>,
+    /// Format for any dates shown, overriding the config.
+    #[arg(long)]
+    date_format: Option<String>,
+    /// Leave out the buffer set in the config.
+    #[arg(long)]
+    ignore_buffer: bool,
+    /// Don't colour the balance.
+    #[arg(long)]
+    no_color: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingSource {
+    Default,
+    Config,
+    Locale,
+    Env,
+    Flag,
+}
+
+impl std::fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self {
+            Self::Default => "default",
+            Self::Config => "config",
+            Self::Locale => "locale",
+            Self::Env => "env",
+            Self::Flag => "flag",
+        };
+        write!(f, "{source}")
+    }
+}
+
+/// The effective value of each setting and where it came from, with flags taking precedence over
+/// the environment, then `--locale`, then the config file.
+///
+/// Config values equal to the default are reported as coming from the default.
+fn effective_settings(
+    args: &ConfigArgs,
+    config: &Config,
+    locale: Option<Locale>,
+    no_color_env: bool,
+) -> Vec<(&'static str, String, SettingSource)> {
+    let defaults = Config::default();
+    let from_config = |is_default: bool| {
+        if is_default {
+            SettingSource::Default
+        } else {
+            SettingSource::Config
+        }
+    };
+    let from_locale = |is_default: bool| {
+        if locale.is_some() {
+            SettingSource::Locale
+        } else {
+            from_config(is_default)
+        }
+    };
+    let config = &localised(config.clone(), locale);
+    let money = config.money_format();
+
+    let reset_day = args.reset_day.map_or((18, SettingSource::Default), |day| {
+        (day, SettingSource::Flag)
+    });
+    let round_day_strategy = args.round_day_strategy.map_or_else(
+        || {
+            (
+                config.round_day_strategy,
+                from_config(config.round_day_strategy == defaults.round_day_strategy),
+            )
+        },
+        |strategy| (strategy, SettingSource::Flag),
+    );
+    let date_format = args.date_format.clone().map_or_else(
+        || {
+            (
+                config.date_format.clone(),
+                from_locale(config.date_format == defaults.date_format),
+            )
+        },
+        |format| (format, SettingSource::Flag),
+    );
+    let buffer = if args.ignore_buffer {
+        (Decimal::ZERO, SettingSource::Flag)
+    } else {
+        (config.buffer, from_config(config.buffer == defaults.buffer))
+    };
+    let color = if args.no_color {
+        ("off", SettingSource::Flag)
+    } else if no_color_env {
+        ("off", SettingSource::Env)
+    } else if config.color_thresholds.is_some() {
+        ("on", SettingSource::Config)
+    } else {
+        ("off", SettingSource::Default)
+    };
+
+    vec![
+        ("reset_day", reset_day.0.to_string(), reset_day.1),
+        (
+            "currency",
+            money.symbol.clone(),
+            from_config(money.symbol == defaults.money_format().symbol),
+        ),
+        (
+            "currency_position",
+            format!("{:?}", config.currency_position),
+            from_locale(config.currency_position == defaults.currency_position),
+        ),
+        (
+            "rounding",
+            format!("{:?}", config.rounding),
+            from_config(config.rounding == defaults.rounding),
+        ),
+        ("buffer", money.format(buffer.0), buffer.1),
+        (
+            "round_day_strategy",
+            format!("{:?}", round_day_strategy.0),
+            round_day_strategy.1,
+        ),
+        ("date_format", date_format.0, date_format.1),
+        ("color", color.0.to_owned(), color.1),
+    ]
+}
+
+#[derive(Args)]
+struct BudgetArgs {
+    /// Expected spending on top of the bills this month.
+    #[arg(short, long, value_parser = amount_validation)]
+    spend: Option<Decimal>,
+}
+
+fn budget_report(
+    args: &BudgetArgs,
+    budget: Option<Decimal>,
+    payments: &Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+) -> anyhow::Result<Vec<String>> {
+    let budget = budget.ok_or_else(|| anyhow!("no budget set, add `budget` to the config"))?;
+
+    let bills = payments.monthly_total(today);
+    let outgoings = bills + args.spend.unwrap_or_default();
+
+    Ok(vec![
+        format!("Outgoings: {}", money.format(outgoings)),
+        format!("Budget: {}", money.format(budget)),
+        format!("Headroom: {}", money.format(budget - outgoings)),
+    ])
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Only print the total for each category, without the bills in it.
+    #[arg(short, long)]
+    group_total_only: bool,
+    /// Whether to include bills with a zero amount.
+    #[arg(long)]
+    include_zero: bool,
+    /// Print how many bills are in each category, most first, rather than what they cost.
+    #[arg(long, conflicts_with = "group_total_only")]
+    count_by_category: bool,
+    #[command(flatten)]
+    filter: NameFilter,
+}
+
+/// Label used in `stats` for bills without a category.
+const UNCATEGORISED: &str = "Uncategorised";
+
+fn stats_report(
+    args: &StatsArgs,
+    payments: &Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+) -> Vec<String> {
+    let mut categories: BTreeMap<&str, Vec<&Payment>> = BTreeMap::new();
+    let included =
+        |p: &&Payment| (args.include_zero || !p.amount.is_zero()) && args.filter.matches(p);
+    for payment in payments.retain_active(today).into_iter().filter(included) {
+        let category = payment.category.as_deref().unwrap_or(UNCATEGORISED);
+        categories.entry(category).or_default().push(payment);
+    }
+
+    if args.count_by_category {
+        let mut counts: Vec<(&str, usize)> = categories
+            .into_iter()
+            .map(|(category, bills)| (category, bills.len()))
+            .collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        return counts
+            .into_iter()
+            .map(|(category, count)| format!("{category}: {count}"))
+            .collect();
+    }
+
+    let mut totals: Vec<(&str, Decimal, Vec<&Payment>)> = categories
+        .into_iter()
+        .map(|(category, mut bills)| {
+            bills.sort();
+            let total = bills.iter().map(|p| p.monthly_cost()).sum();
+            (category, total, bills)
+        })
+        .collect();
+    totals.sort_by_key(|(_, total, _)| std::cmp::Reverse(*total));
+
+    let mut lines = vec![];
+    for (category, total, bills) in totals {
+        lines.push(format!("{category}: {}", money.format(total)));
+        if !args.group_total_only {
+            lines.extend(
+                bills
+                    .iter()
+                    .map(|p| format!("  {} {}", p.name, money.format(p.monthly_cost()))),
+            );
+        }
+    }
+    lines
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// CSV file with `name`, `amount` and `day_paid` columns, and optionally `category`, or a QIF
+    /// file with `--format qif`.
+    #[arg(required_unless_present = "from_clipboard")]
+    path: Option<PathBuf>,
+    /// Read the bills from the clipboard instead of a file. Tab separated text, as copied from a
+    /// spreadsheet, is read as well as CSV. Needs one of `wl-paste`, `xclip`, `xsel`, `pbpaste`
+    /// or PowerShell installed.
+    #[arg(long, conflicts_with = "path")]
+    from_clipboard: bool,
+    /// Round amounts to two decimal places rather than rejecting them.
+    #[arg(short, long)]
+    round: bool,
+    /// Update the amount and day of bills that already exist rather than rejecting them.
+    #[arg(short, long)]
+    merge: bool,
+    /// Format of the file being imported.
+    #[arg(short, long, value_enum, default_value_t = ImportFormat::Csv)]
+    format: ImportFormat,
+    /// Print the bills that would be imported without saving them.
+    #[arg(long)]
+    dry_run: bool,
+    /// Character separating the fields of a CSV file, such as `;`.
+    #[arg(short, long, default_value_t = ',', value_parser = delimiter_validation)]
+    delimiter: char,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Character separating the fields, such as `;`.
+    #[arg(short, long, default_value_t = ',', value_parser = delimiter_validation)]
+    delimiter: char,
+    /// Whether to include archived bills, and bills that have ended or not yet started.
+    #[arg(long)]
+    include_archived: bool,
+    /// Replace names and categories with placeholders and round amounts up to the next ten, to
+    /// share the shape of your bills without the details.
+    #[arg(long)]
+    anonymize: bool,
+}
+
+fn delimiter_validation(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some('"' | '\n' | '\r'), None) => Err(format!("`{s}` can't be used as a delimiter")),
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("`{s}` isn't a single character")),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum MergeStrategy {
+    /// Keep your bill when both configs have one with the same name.
+    KeepMine,
+    /// Replace your bill with theirs when both configs have one with the same name.
+    KeepTheirs,
+    /// Refuse to merge if both configs have a bill with the same name.
+    Error,
+}
+
+#[derive(Args)]
+struct MergeArgs {
+    /// Config file to merge bills from.
+    path: PathBuf,
+    /// What to do with bills that are in both configs.
+    #[arg(short, long, value_enum, default_value_t = MergeStrategy::Error)]
+    strategy: MergeStrategy,
+}
+
+fn merge_payments(
+    strategy: MergeStrategy,
+    mut mine: Payments,
+    theirs: Payments,
+) -> anyhow::Result<Payments> {
+    if strategy == MergeStrategy::Error {
+        let index = mine.index();
+        let collisions: Vec<&str> = theirs
+            .iter()
+            .filter(|p| index.contains_key(p.name.as_str()))
+            .map(|p| p.name.as_str())
+            .collect();
+        if !collisions.is_empty() {
+            return Err(BalanceError::AlreadyExists(format!(
+                "both configs have {}",
+                collisions.join(", ")
+            ))
+            .into());
+        }
+    }
+
+    for payment in theirs {
+        match mine.iter_mut().find(|p| **p == payment) {
+            Some(existing) if strategy == MergeStrategy::KeepTheirs => *existing = payment,
+            Some(_) => {}
+            None => mine.push(payment),
+        }
+    }
+    Ok(mine)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ImportFormat {
+    /// A CSV file with a header row.
+    Csv,
+    /// A QIF bank export, with a bill suggested for each payee paid.
+    Qif,
+}
+
+/// Text to import, read from the clipboard with `read_clipboard` or from the file given.
+fn import_contents(
+    args: &ImportArgs,
+    read_clipboard: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    if args.from_clipboard {
+        let contents = read_clipboard()?;
+        if contents.trim().is_empty() {
+            return Err(anyhow!(
+                "the clipboard is empty, copy the bills to import first"
+            ));
+        }
+        return Ok(contents);
+    }
+    let path = args
+        .path
+        .as_ref()
+        .ok_or_else(|| anyhow!("no file to import was given"))?;
+    Ok(std::fs::read_to_string(path)?)
+}
+
+fn parse_import(
+    args: &ImportArgs,
+    contents: &str,
+    existing: &[Payment],
+) -> anyhow::Result<Payments> {
+    let pasted_table =
+        args.from_clipboard && contents.lines().next().is_some_and(|l| l.contains('\t'));
+    let delimiter = if pasted_table { '\t' } else { args.delimiter };
+    match args.format {
+        ImportFormat::Csv => import::parse_payments(contents, delimiter, args.round, existing),
+        ImportFormat::Qif => import::parse_qif(contents, args.round, existing),
+    }
+}
+
+fn import_entries(
+    args: &ImportArgs,
+    contents: &str,
+    mut payments: Payments,
+) -> anyhow::Result<Payments> {
+    if !args.merge {
+        let imported = parse_import(args, contents, &payments)?;
+        payments.extend(imported);
+        return Ok(payments);
+    }
+
+    for imported in parse_import(args, contents, &[])? {
+        match payments.iter_mut().find(|p| **p == imported) {
+            Some(existing) => {
+                existing.amount = imported.amount;
+                existing.day_paid = imported.day_paid;
+                if imported.category.is_some() {
+                    existing.category = imported.category;
+                }
+            }
+            None => payments.push(imported),
+        }
+    }
+    Ok(payments)
+}
+
+/// Warning shown after a change when the monthly bills come to more than the configured income.
+fn income_warning(
+    income: Option<Decimal>,
+    payments: &Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+) -> Option<String> {
+    let income = income?;
+    let outgoings = payments.monthly_total(today);
+
+    (outgoings > income).then(|| {
+        format!(
+            "warning: monthly bills of {} exceed income of {}",
+            money.format(outgoings),
+            money.format(income)
+        )
+    })
+}
+
+/// Income left each month after the bills, and what share of the income that is, as shown by
+/// `stats`.
+fn net_monthly(
+    income: Option<Decimal>,
+    payments: &Payments,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+) -> Option<String> {
+    let income = income?;
+    let net = income - payments.monthly_total(today);
+    if income.is_zero() {
+        return Some(format!("Net monthly: {}", money.format(net)));
+    }
+    let percent = (net / income * Decimal::ONE_HUNDRED)
+        .round_dp_with_strategy(1, RoundingStrategy::MidpointAwayFromZero);
+    Some(format!(
+        "Net monthly: {} ({percent}% of income)",
+        money.format(net)
+    ))
+}
+
+/// Describes a change to a single payment, such as `Adjust Water: £20.00 → £22.00`.
+fn payment_changes(before: &Payment, after: &Payment, money: &MoneyFormat) -> Vec<String> {
+    let name = &after.name;
+    let mut changes = vec![];
+    if before.amount != after.amount {
+        changes.push(format!(
+            "Adjust {name}: {} → {}",
+            money.format(before.amount),
+            money.format(after.amount)
+        ));
+    }
+    if before.day_paid != after.day_paid {
+        changes.push(format!(
+            "Adjust {name}: day {} → {}",
+            before.day_paid, after.day_paid
+        ));
+    }
+    if before.category != after.category {
+        changes.push(format!(
+            "Adjust {name}: category {} → {}",
+            before.category.as_deref().unwrap_or("none"),
+            after.category.as_deref().unwrap_or("none")
+        ));
+    }
+    if before.archived != after.archived {
+        let action = if after.archived {
+            "Archive"
+        } else {
+            "Unarchive"
+        };
+        changes.push(format!("{action} {name}"));
+    }
+
+    let shown = |value: Option<String>| value.unwrap_or_else(|| "none".to_owned());
+    let date = |date: Option<NaiveDate>| shown(date.map(|d| d.to_string()));
+    let change = |change: Option<AmountChange>| {
+        shown(change.map(|c| format!("{} until {}", money.format(c.previous), c.effective)))
+    };
+    let fields = [
+        (
+            "frequency",
+            format!("{:?}", before.frequency),
+            format!("{:?}", after.frequency),
+        ),
+        (
+            "weekend rule",
+            format!("{:?}", before.weekend_rule),
+            format!("{:?}", after.weekend_rule),
+        ),
+        (
+            "share",
+            shown(before.share_percent.map(|p| format!("{p}%"))),
+            shown(after.share_percent.map(|p| format!("{p}%"))),
+        ),
+        (
+            "start date",
+            date(before.start_date),
+            date(after.start_date),
+        ),
+        ("end date", date(before.end_date), date(after.end_date)),
+        (
+            "reset day",
+            shown(before.reset_day.map(|d| d.to_string())),
+            shown(after.reset_day.map(|d| d.to_string())),
+        ),
+        (
+            "description",
+            shown(before.description.clone()),
+            shown(after.description.clone()),
+        ),
+        (
+            "previous amount",
+            change(before.change),
+            change(after.change),
+        ),
+    ];
+    for (field, old, new) in fields {
+        if old != new {
+            changes.push(format!("Adjust {name}: {field} {old} → {new}"));
+        }
+    }
+    changes
+}
+
+/// One line for each payment added, removed or changed between `before` and `after`.
+///
+/// Payments are matched by name, preferring an unchanged payment when a name appears more than
+/// once, so removing a duplicate is reported as a removal rather than an adjustment.
+fn change_summary(before: &[Payment], after: &[Payment], money: &MoneyFormat) -> Vec<String> {
+    let mut matched: Vec<Option<usize>> = vec![None; before.len()];
+    let mut used = vec![false; after.len()];
+
+    for unchanged_only in [true, false] {
+        for (i, old) in before.iter().enumerate() {
+            if matched[i].is_some() {
+                continue;
+            }
+            let found = after.iter().enumerate().position(|(j, new)| {
+                !used[j]
+                    && new.name == old.name
+                    && (!unchanged_only || payment_changes(old, new, money).is_empty())
+            });
+            if let Some(j) = found {
+                matched[i] = Some(j);
+                used[j] = true;
+            }
+        }
+    }
+
+    let mut lines = vec![];
+    for (old, new) in before.iter().zip(&matched) {
+        match new {
+            Some(j) => lines.extend(payment_changes(old, &after[*j], money)),
+            None => lines.push(format!("Remove {}: {}", old.name, money.format(old.amount))),
+        }
+    }
+    for (new, _) in after.iter().zip(&used).filter(|(_, used)| !**used) {
+        lines.push(format!("Add {}: {}", new.name, money.format(new.amount)));
+    }
+    lines
+}
+
+/// Asks on stdin whether to save the summarised changes.
+fn confirm_on_stdin(summary: &[String]) -> anyhow::Result<bool> {
+    for line in summary {
+        eprintln!("{line}");
+    }
+    eprint!("Save these changes? [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Stores the config if the changes to its payments are confirmed, returning whether it was stored.
+/// With `backup`, the config being replaced is backed up first, once the changes are confirmed.
+fn store_confirmed(
+    store: &mut impl ConfigStore,
+    before: &[Payment],
+    config: &Config,
+    backup: bool,
+    money: &MoneyFormat,
+    confirm: &mut impl FnMut(&[String]) -> anyhow::Result<bool>,
+) -> anyhow::Result<bool> {
+    let summary = change_summary(before, &config.payments, money);
+    if !summary.is_empty() && !confirm(&summary)? {
+        eprintln!("Aborted, no changes were saved");
+        return Ok(false);
+    }
+    if backup {
+        store.backup()?;
+    }
+    store.store(config)?;
+    Ok(true)
+}
+
+/// `config` formatted for `locale`, if one was given.
+fn localised(config: Config, locale: Option<Locale>) -> Config {
+    match locale {
+        Some(locale) => locale.apply(config),
+        None => config,
+    }
+}
+
+fn run(
+    command: &Commands,
+    options: RunOptions,
+    store: &mut impl ConfigStore,
+    confirm: &mut impl FnMut(&[String]) -> anyhow::Result<bool>,
+) -> anyhow::Result<u8> {
+    let RunOptions { locale, if_changed } = options;
+    let is_terminal = std::io::stdout().is_terminal();
+    if let Commands::Compute(args) = command {
+        if args.watch_config {
+            let store = &*store;
+            let recompute = || {
+                let config = localised(store.load()?, locale);
+                let money =
+                    OutputFormat::resolve(args.format, is_terminal).money(&config.money_format());
+                let output = compute_balance(args, &config, &money)?;
+                print_compute(args, &output, args.color(is_terminal));
+                Ok(())
+            };
+            let wait = || {
+                std::thread::sleep(WATCH_INTERVAL);
+                true
+            };
+            return store::watch(|| store.modified(), recompute, wait).map(|()| 0);
+        }
+    }
+    let compute_cache = match command {
+        Commands::Compute(args) => {
+            compute_cache_key(args, locale, store, is_terminal)?.zip(store.cache_path()?)
+        }
+        _ => None,
+    };
+    if let (Commands::Compute(args), Some((key, path))) = (command, &compute_cache) {
+        if let Some(output) = cache::load(path, key) {
+            return Ok(print_compute(args, &output, args.color(is_terminal)));
+        }
+    }
+
+    let _lock = if command.takes_lock() {
+        store.lock()?
+    } else {
+        None
+    };
+    let mut config = store.load()?;
+    let shown = localised(config.clone(), locale);
+    let money = shown.money_format();
+    let today = utils::current_date(chrono::Utc::now(), &chrono::Local, false);
+    // The config as loaded, which changes are summarised against.
+    let stored = config.clone();
+    let mut save = |store: &mut _, before: &Config, config: &Config, backup: bool| {
+        if if_changed && config.same_contents(before) {
+            eprintln!("No change, the config wasn't saved");
+            return Ok(false);
+        }
+        store_confirmed(store, &before.payments, config, backup, &money, confirm)
+    };
+
+    let status = match command {
+        Commands::Compute(args) => {
+            let money = OutputFormat::resolve(args.format, is_terminal).money(&money);
+            if !args.balances.is_empty() {
+                for line in balance_table(args, &config, &money) {
+                    println!("{line}");
+                }
+                return Ok(0);
+            }
+            let output = compute_balance(args, &shown, &money)?;
+            if let Some((key, path)) = &compute_cache {
+                if let Err(e) = cache::store(path, key, &output) {
+                    eprintln!("warning: couldn't cache the result: {e}");
+                }
+            }
+            print_compute(args, &output, args.color(is_terminal))
+        }
+        Commands::Adjust(args) => {
+            if let Some(day_paid) = args.day_paid {
+                config.check_blackout(day_paid)?;
+            }
+            config.payments = adjust_entry(args, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            if let Some(warning) = income_warning(config.income, &config.payments, &today, &money) {
+                eprintln!("{warning}");
+            }
+            0
+        }
+        Commands::Add(args) => {
+            config.check_blackout(args.day_paid)?;
+            config.payments = add_entry(args, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            if let Some(warning) = income_warning(config.income, &config.payments, &today, &money) {
+                eprintln!("{warning}");
+            }
+            0
+        }
+        Commands::Remove(args) => {
+            config.payments = remove_entry(args, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            0
+        }
+        Commands::Dedupe(args) => {
+            let (payments, removed) = dedupe_entries(args, config.payments);
+            config.payments = payments;
+            if !save(store, &stored, &config, true)? {
+                return Ok(0);
+            }
+            println!("Removed {removed} duplicate bills");
+            0
+        }
+        Commands::Archive(args) => {
+            config.payments = set_archived(args, true, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            0
+        }
+        Commands::Unarchive(args) => {
+            config.payments = set_archived(args, false, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            0
+        }
+        Commands::Toggle(args) => {
+            let (payments, archived) = toggle_archived(args, config.payments)?;
+            config.payments = payments;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            let state = if archived { "archived" } else { "active" };
+            println!("{} is now {state}", args.name);
+            0
+        }
+        Commands::Show(args) => {
+            println!("{}", show_entry(args, &config.payments, &money)?);
+            0
+        }
+        Commands::List(args) => {
+            let money = OutputFormat::resolve(args.format, is_terminal).money(&money);
+            for line in list_payments(
+                args,
+                &mut config.payments,
+                &today,
+                &money,
+                &shown.date_format,
+                config.round_day_strategy,
+            )? {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Total(args) => {
+            for line in total_lines(
+                args,
+                &config.payments,
+                &today,
+                config.round_day_strategy,
+                &money,
+            ) {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Edit(_) => {
+            store.edit()?;
+            0
+        }
+        Commands::Tui(args) => {
+            let mut state = tui::TuiState::new(
+                config.payments.clone(),
+                args.reset_day,
+                config.round_day_strategy,
+            );
+            tui::run(
+                &mut state,
+                &today,
+                &money,
+                &mut |line| std::io::stdin().read_line(line),
+                &mut std::io::stdout(),
+                &mut |state| {
+                    // Edits are applied to the config as it is now, so changes made by other
+                    // commands while the tui was open aren't lost.
+                    let _lock = store.lock()?;
+                    let before = store.load()?;
+                    let mut latest = before.clone();
+                    state.apply_edits(&mut latest.payments)?;
+                    save(store, &before, &latest, true)
+                },
+            )?;
+            0
+        }
+        Commands::Schema(_) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema::config_schema())?
+            );
+            0
+        }
+        Commands::Import(args) => {
+            let contents = import_contents(args, clipboard::read)?;
+            if args.dry_run {
+                let existing = if args.merge {
+                    &[]
+                } else {
+                    &config.payments[..]
+                };
+                for payment in parse_import(args, &contents, existing)? {
+                    println!(
+                        "{} {}, day paid: {}",
+                        payment.name,
+                        money.format(payment.amount),
+                        payment.day_paid
+                    );
+                }
+                return Ok(0);
+            }
+            config.payments = import_entries(args, &contents, config.payments)?;
+            if !save(store, &stored, &config, false)? {
+                return Ok(0);
+            }
+            0
+        }
+        Commands::Export(args) => {
+            let mut payments: Vec<&Payment> = config
+                .payments
+                .iter()
+                .filter(|p| args.include_archived || p.is_active(&today))
+                .collect();
+            let anonymized;
+            if args.anonymize {
+                anonymized = export::anonymize(&payments);
+                payments = anonymized.iter().collect();
+            }
+            print!("{}", export::payments_csv(&payments, args.delimiter));
+            0
+        }
+        Commands::Config(args) => {
+            let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            for (name, value, source) in effective_settings(args, &config, locale, no_color_env) {
+                println!("{name}: {value} ({source})");
+            }
+            0
+        }
+        Commands::Merge(args) => {
+            let theirs = payment::read_config(&args.path)?;
+            config.payments = merge_payments(args.strategy, config.payments, theirs.payments)?;
+            if !save(store, &stored, &config, true)? {
+                return Ok(0);
+            }
+            0
+        }
+        Commands::Budget(args) => {
+            for line in budget_report(args, config.budget, &config.payments, &today, &money)? {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Stats(args) => {
+            for line in stats_report(args, &config.payments, &today, &money) {
+                println!("{line}");
+            }
+            if let Some(line) = net_monthly(config.income, &config.payments, &today, &money) {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Forecast(args) => {
+            for line in forecast_balances(args, config.payments, config.round_day_strategy, &money)
+            {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Due(args) => {
+            let today = args.as_of.unwrap_or(today);
+            for line in due_report(args, &config.payments, &today, &money, &shown.date_format)? {
+                println!("{line}");
+            }
+            0
+        }
+        Commands::Remind(args) => {
+            let today = args.as_of.unwrap_or(today);
+            write_reminders(args, &config.payments, &today)?;
+            0
+        }
+        Commands::Doctor(args) => {
+            let rules = args.rules();
+            let problems: Vec<String> = rules
+                .iter()
+                .flat_map(|rule| rule.check(&config.payments))
+                .collect();
+            for problem in &problems {
+                println!("{problem}");
+            }
+            if problems.is_empty() {
+                println!("No problems found");
+                0
+            } else if args.fix {
+                config.payments = rules
+                    .iter()
+                    .fold(config.payments, |payments, rule| rule.fix(payments));
+                if !save(store, &stored, &config, false)? {
+                    return Ok(PROBLEMS_FOUND_STATUS);
+                }
+                println!("Fixed {} problems", problems.len());
+                0
+            } else {
+                PROBLEMS_FOUND_STATUS
+            }
+        }
+    };
+
+    Ok(status)
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    CURRENCY_SYMBOL.get_or_init(payment::stored_currency_symbol);
+    let args = App::parse();
+
+    let interactive = std::io::stdin().is_terminal();
+    let result = run(
+        &args.command,
+        RunOptions {
+            locale: args.locale,
+            if_changed: args.if_changed,
+        },
+        &mut ConfyStore {
+            strict: args.strict,
+        },
+        &mut |summary: &[String]| {
+            if args.yes || !interactive {
+                Ok(true)
+            } else {
+                confirm_on_stdin(summary)
+            }
+        },
+    );
+
+    match (result, args.error_format) {
+        (Ok(status), _) => Ok(ExitCode::from(status)),
+        (Err(e), ErrorFormat::Json) => {
+            eprintln!("{}", error::error_json(&e));
+            Ok(ExitCode::FAILURE)
+        }
+        (Err(e), ErrorFormat::Text) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::{
+        money::Position,
+        payment::{Frequency, WeekendRule},
+        store::MemoryStore,
+        utils::DEFAULT_DATE_FORMAT,
+    };
+
+    #[test]
+    fn verify_cli() {
+        App::command().debug_assert();
+    }
+
+    fn compute_args() -> ComputeArgs {
+        ComputeArgs {
+            balance: Some(Decimal::new(10000, 2)),
+            from_balance_file: None,
+            balances: vec![],
+            accounts: vec![],
+            reset_day: 18,
+            as_of: NaiveDate::from_ymd_opt(2023, 1, 31),
+            utc: false,
+            explain: false,
+            since_reset: false,
+            prorate: false,
+            add_back: false,
+            rounding_report: false,
+            watch_config: false,
+            ignore_buffer: false,
+            relative: false,
+            reset_today: false,
+            as_percentage_of: None,
+            compact: false,
+            no_color: false,
+            zero_date: false,
+            zero_date_months: 12,
+            round_day_strategy: None,
+            fail_on_negative: false,
+            warn_threshold: None,
+            fail_on_warn: false,
+            format: None,
+            daily: false,
+            round_to: 2,
+            date_format: None,
+            no_cache: false,
+        }
+    }
+
+    fn adjust_args() -> AdjustArgs {
+        AdjustArgs {
+            name: None,
+            category: None,
+            amount: None,
+            by_percent: None,
+            day_paid: None,
+            normalize: false,
+            effective: None,
+            create: false,
+        }
+    }
+
+    fn list_args() -> ListArgs {
+        ListArgs {
+            amount: false,
+            day_paid: false,
+            category: None,
+            summary: false,
+            group_by_day: false,
+            min_amount: None,
+            max_amount: None,
+            format: None,
+            include_archived: false,
+            due_dates: false,
+            limit: None,
+            offset: 0,
+            include_zero: false,
+            exclude_zero: false,
+            sort: ListSort::Name,
+            reset_day: 18,
+            template: None,
+            tsv: false,
+            only_name: false,
+            filter: NameFilter::default(),
+        }
+    }
+
+    fn energy_config() -> Config {
+        Config {
+            payments: energy_payments(),
+            ..Config::default()
         }
-        Commands::Edit(_) => edit_config(),
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 1, 19).unwrap()
+    }
+
+    fn energy_payments() -> Payments {
+        vec![
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6).with_category("Energy"),
+            Payment::new("Electric".to_owned(), Decimal::new(3550, 2), 8).with_category("Energy"),
+            Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3),
+        ]
+    }
+
+    #[test]
+    fn adjust_category_flat_amount() {
+        let args = AdjustArgs {
+            category: Some("Energy".to_owned()),
+            amount: Some(Decimal::new(4000, 2)),
+            ..adjust_args()
+        };
+
+        let payments = adjust_entry(&args, energy_payments()).unwrap();
+
+        let amounts: Vec<Decimal> = payments.iter().map(|p| p.amount).collect();
+        assert_eq!(
+            amounts,
+            vec![
+                Decimal::new(4000, 2),
+                Decimal::new(4000, 2),
+                Decimal::new(1500, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn adjust_category_by_percent() {
+        let args = AdjustArgs {
+            category: Some("Energy".to_owned()),
+            by_percent: Some(Decimal::new(10, 0)),
+            ..adjust_args()
+        };
+
+        let payments = adjust_entry(&args, energy_payments()).unwrap();
+
+        let amounts: Vec<Decimal> = payments.iter().map(|p| p.amount).collect();
+        assert_eq!(
+            amounts,
+            vec![
+                Decimal::new(2200, 2),
+                Decimal::new(3905, 2),
+                Decimal::new(1500, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn adjust_single_bill_up_by_percent() {
+        let args = AdjustArgs {
+            name: Some("Water".to_owned()),
+            by_percent: Some(Decimal::new(10, 0)),
+            ..adjust_args()
+        };
+        let payments = vec![Payment::new("Water".to_owned(), Decimal::new(1234, 2), 3)];
+
+        let payments = adjust_entry(&args, payments).unwrap();
+
+        assert_eq!(payments[0].amount, Decimal::new(1357, 2));
+    }
+
+    #[test]
+    fn adjust_single_bill_down_by_percent() {
+        let args = AdjustArgs {
+            name: Some("Water".to_owned()),
+            by_percent: Some(Decimal::new(-5, 0)),
+            ..adjust_args()
+        };
+        let payments = vec![Payment::new("Water".to_owned(), Decimal::new(1010, 2), 3)];
+
+        let payments = adjust_entry(&args, payments).unwrap();
+
+        assert_eq!(payments[0].amount, Decimal::new(960, 2));
+    }
+
+    #[test]
+    fn amount_and_by_percent_are_exclusive() {
+        let result = App::try_parse_from([
+            "balance",
+            "adjust",
+            "Water",
+            "--amount",
+            "10",
+            "--by-percent",
+            "5",
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn adjust_unknown_category() {
+        let args = AdjustArgs {
+            category: Some("Travel".to_owned()),
+            amount: Some(Decimal::new(4000, 2)),
+            ..adjust_args()
+        };
+
+        assert!(adjust_entry(&args, energy_payments()).is_err());
+    }
+
+    #[test]
+    fn list_summary_of_filtered_bills() {
+        let args = ListArgs {
+            category: Some("Energy".to_owned()),
+            summary: true,
+            ..list_args()
+        };
+
+        let lines = list_payments(
+            &args,
+            &mut energy_payments(),
+            &NaiveDate::from_ymd_opt(2023, 1, 19).unwrap(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(lines, vec!["Electric", "Gas", "Total: £55.50"]);
+    }
+
+    #[test]
+    fn list_summary_matches_total() {
+        let args = ListArgs {
+            summary: true,
+            ..list_args()
+        };
+        let rent = Payment {
+            share_percent: Some(Decimal::new(50, 0)),
+            ..Payment::new("Rent".to_owned(), Decimal::new(60000, 2), 3)
+        };
+        let mut payments = vec![rent, semi_monthly("Nursery", 1, 15)];
+
+        let lines = list_payments(
+            &args,
+            &mut payments,
+            &today(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+        let total = total_payments(&total_args(), &payments, &today(), DayStrategy::Clamp);
+
+        assert_eq!(lines.last().unwrap(), "Total: £900.00");
+        assert_eq!(MoneyFormat::default().format(total), "£900.00");
+    }
+
+    #[test]
+    fn explain_known_config() {
+        let args = ComputeArgs {
+            explain: true,
+            ..compute_args()
+        };
+        let payment_manager = args
+            .payment_manager(&Config {
+                payments: vec![
+                    Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+                    Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+                ],
+                ..Config::default()
+            })
+            .unwrap();
+
+        let report = payment_manager.report(&args.current_day());
+        let lines = explain_report(&report, &MoneyFormat::default(), "%d/%m/%Y").unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Today is day 13 of the bill cycle, counting the reset day as day 0.",
+                "The cycle next resets on 18/02/2023.",
+                "Phone is paid on day 10, which is not after today, so it has already come out.",
+                "Water is paid on day 16, which is after today, so £20.00 is still to come out.",
+            ]
+        );
+    }
+
+    #[test]
+    fn explain_shows_your_share_of_shared_bills() {
+        let args = ComputeArgs {
+            explain: true,
+            ..compute_args()
+        };
+        let rent = Payment {
+            share_percent: Some(Decimal::new(50, 0)),
+            ..Payment::new("Rent".to_owned(), Decimal::new(60000, 2), 3)
+        };
+        let payment_manager = args
+            .payment_manager(&Config {
+                payments: vec![rent],
+                ..Config::default()
+            })
+            .unwrap();
+
+        let report = payment_manager.report(&args.current_day());
+        let lines = explain_report(&report, &MoneyFormat::default(), "%d/%m/%Y").unwrap();
+
+        assert_eq!(
+            lines[2],
+            "Rent is paid on day 16, which is after today, so £300.00 is still to come out."
+        );
+        assert_eq!(report.remaining, Decimal::new(-20000, 2));
+    }
+
+    #[test]
+    fn explain_uses_the_bills_own_reset_day() {
+        let args = ComputeArgs {
+            explain: true,
+            ..compute_args()
+        };
+        let card = Payment {
+            reset_day: Some(28),
+            ..Payment::new("Card".to_owned(), Decimal::new(1000, 2), 20)
+        };
+        let payment_manager = args
+            .payment_manager(&Config {
+                payments: vec![card],
+                ..Config::default()
+            })
+            .unwrap();
+
+        let report = payment_manager.report(&args.current_day());
+        let lines = explain_report(&report, &MoneyFormat::default(), "%d/%m/%Y").unwrap();
+
+        assert_eq!(
+            lines[2],
+            "Card is paid on day 23 of its own cycle from day 28, which is after today, day 3 of \
+             that cycle, so £10.00 is still to come out."
+        );
+        assert!(report.details[0].leftover);
+    }
+
+    #[test]
+    fn fail_on_negative_exit_status() {
+        let args = ComputeArgs {
+            fail_on_negative: true,
+            ..compute_args()
+        };
+
+        assert_eq!(
+            args.exit_status(Decimal::new(-1, 2)),
+            NEGATIVE_BALANCE_STATUS
+        );
+        assert_eq!(args.exit_status(Decimal::ZERO), 0);
+        assert_eq!(args.exit_status(Decimal::new(1, 2)), 0);
+    }
+
+    #[test]
+    fn negative_balance_exits_cleanly_by_default() {
+        assert_eq!(compute_args().exit_status(Decimal::new(-1, 2)), 0);
+    }
+
+    #[test]
+    fn import_rejects_existing_name() {
+        let args = ImportArgs {
+            path: None,
+            from_clipboard: false,
+            round: false,
+            merge: false,
+            format: ImportFormat::Csv,
+            dry_run: false,
+            delimiter: ',',
+        };
+
+        let result = import_entries(
+            &args,
+            "name,amount,day_paid\nGas,25.00,6\n",
+            energy_payments(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn forecast_with_income() {
+        let args = ForecastArgs {
+            balance: Decimal::new(10000, 2),
+            start_balance: false,
+            current_balance: false,
+            reset_day: 18,
+            income: Some(Decimal::new(5000, 2)),
+            cycles: 1,
+            as_of: NaiveDate::from_ymd_opt(2023, 1, 19),
+            group_by: None,
+        };
+
+        let lines = forecast_balances(
+            &args,
+            energy_payments(),
+            DayStrategy::Clamp,
+            &MoneyFormat::default(),
+        );
+
+        assert_eq!(lines, vec!["This cycle: £29.50", "Cycle +1: £9.00"]);
+    }
+
+    #[test]
+    fn forecast_grouped_by_month() {
+        let args = ForecastArgs {
+            balance: Decimal::new(10000, 2),
+            start_balance: false,
+            current_balance: false,
+            reset_day: 18,
+            income: Some(Decimal::new(8000, 2)),
+            cycles: 3,
+            as_of: NaiveDate::from_ymd_opt(2022, 11, 19),
+            group_by: Some(ForecastGroup::Month),
+        };
+
+        let lines = forecast_balances(
+            &args,
+            energy_payments(),
+            DayStrategy::Clamp,
+            &MoneyFormat::default(),
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                "Dec 2022: income £80.00, bills £70.50, end balance £39.00",
+                "Jan 2023: income £80.00, bills £70.50, end balance £48.50",
+                "Feb 2023: income £80.00, bills £70.50, end balance £58.00",
+            ]
+        );
+    }
+
+    /// A semi-monthly bill whose `day_paid` is left at zero, as it is ignored.
+    fn semi_monthly(name: &str, first: isize, second: isize) -> Payment {
+        Payment {
+            frequency: Frequency::SemiMonthly { first, second },
+            ..Payment::new(name.to_owned(), Decimal::new(30000, 2), 0)
+        }
+    }
+
+    #[test]
+    fn list_grouped_by_day() {
+        let args = ListArgs {
+            amount: true,
+            group_by_day: true,
+            ..list_args()
+        };
+        let mut payments = energy_payments();
+        payments.push(Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 6));
+        payments.push(Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 31));
+        payments.push(semi_monthly("Rent", 1, 6));
+
+        let lines = list_payments(
+            &args,
+            &mut payments,
+            &NaiveDate::from_ymd_opt(2023, 2, 10).unwrap(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Day 1: £300.00",
+                "  Rent £300.00",
+                "Day 3: £15.00",
+                "  Water £15.00",
+                "Day 6: £330.00",
+                "  Gas £20.00",
+                "  Phone £10.00",
+                "  Rent £300.00",
+                "Day 8: £35.50",
+                "  Electric £35.50",
+                "Day 28: £30.00",
+                "  Gym £30.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_with_currency_suffix() {
+        let money = MoneyFormat {
+            symbol: "€".to_owned(),
+            position: Position::Suffix,
+            ..MoneyFormat::default()
+        };
+
+        let output = compute_balance(&compute_args(), &energy_config(), &money).unwrap();
+
+        assert_eq!(output.lines, vec!["29.50€"]);
+    }
+
+    #[test]
+    fn import_merge_updates_and_adds() {
+        let args = ImportArgs {
+            path: None,
+            from_clipboard: false,
+            round: false,
+            merge: true,
+            format: ImportFormat::Csv,
+            dry_run: false,
+            delimiter: ',',
+        };
+
+        let payments = import_entries(
+            &args,
+            "name,amount,day_paid\nGas,25.00,7\nPhone,10.00,28\n",
+            energy_payments(),
+        )
+        .unwrap();
+
+        let gas = payments.iter().find(|p| p.name == "Gas").unwrap();
+        assert_eq!((gas.amount, gas.day_paid), (Decimal::new(2500, 2), 7));
+        assert_eq!(gas.category.as_deref(), Some("Energy"));
+        assert_eq!(payments.len(), 4);
+        assert!(payments.iter().any(|p| p.name == "Phone"));
+    }
+
+    fn budget_headroom(spend: Option<Decimal>, budget: Decimal) -> String {
+        let lines = budget_report(
+            &BudgetArgs { spend },
+            Some(budget),
+            &energy_payments(),
+            &today(),
+            &MoneyFormat::default(),
+        )
+        .unwrap();
+        lines[2].clone()
+    }
+
+    #[test]
+    fn budget_under() {
+        assert_eq!(
+            budget_headroom(Some(Decimal::new(2000, 2)), Decimal::new(10000, 2)),
+            "Headroom: £9.50"
+        );
+    }
+
+    #[test]
+    fn budget_on() {
+        assert_eq!(
+            budget_headroom(None, Decimal::new(7050, 2)),
+            "Headroom: £0.00"
+        );
+    }
+
+    #[test]
+    fn budget_over() {
+        assert_eq!(
+            budget_headroom(Some(Decimal::new(5000, 2)), Decimal::new(10000, 2)),
+            "Headroom: £-20.50"
+        );
+    }
+
+    #[test]
+    fn budget_missing() {
+        let result = budget_report(
+            &BudgetArgs { spend: None },
+            None,
+            &energy_payments(),
+            &today(),
+            &MoneyFormat::default(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    fn list_names(args: &ListArgs) -> anyhow::Result<Vec<String>> {
+        list_payments(
+            args,
+            &mut energy_payments(),
+            &NaiveDate::from_ymd_opt(2023, 1, 19).unwrap(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+    }
+
+    fn total_args() -> TotalArgs {
+        TotalArgs {
+            category: None,
+            exact: false,
+            future_only: false,
+            reset_day: 18,
+            filter: NameFilter::default(),
+        }
+    }
+
+    /// Runs `command` against `store`, saying yes to every confirmation.
+    fn run_ok(command: &Commands, store: &mut MemoryStore) -> anyhow::Result<u8> {
+        run(command, RunOptions::default(), store, &mut |_| Ok(true))
+    }
+
+    #[test]
+    fn list_min_amount() {
+        let args = ListArgs {
+            min_amount: Some(Decimal::new(2000, 2)),
+            ..list_args()
+        };
+
+        assert_eq!(list_names(&args).unwrap(), vec!["Electric", "Gas"]);
+    }
+
+    #[test]
+    fn list_max_amount() {
+        let args = ListArgs {
+            max_amount: Some(Decimal::new(2000, 2)),
+            ..list_args()
+        };
+
+        assert_eq!(list_names(&args).unwrap(), vec!["Gas", "Water"]);
+    }
+
+    #[test]
+    fn list_amount_range_with_category() {
+        let args = ListArgs {
+            category: Some("Energy".to_owned()),
+            max_amount: Some(Decimal::new(2000, 2)),
+            ..list_args()
+        };
+
+        assert_eq!(list_names(&args).unwrap(), vec!["Gas"]);
+    }
+
+    #[test]
+    fn list_invalid_amount_range() {
+        let args = ListArgs {
+            min_amount: Some(Decimal::new(3000, 2)),
+            max_amount: Some(Decimal::new(2000, 2)),
+            ..list_args()
+        };
+
+        assert!(list_names(&args).is_err());
+    }
+
+    #[test]
+    fn add_then_list_in_memory() {
+        let mut store = MemoryStore::default();
+        let add = App::try_parse_from(["balance", "add", "Gas", "20.00", "6", "-c", "Energy"]);
+        let remove = App::try_parse_from(["balance", "remove", "Water"]);
+
+        run_ok(&add.unwrap().command, &mut store).unwrap();
+        store
+            .config
+            .payments
+            .push(Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3));
+        run_ok(&remove.unwrap().command, &mut store).unwrap();
+
+        let mut config = store.load().unwrap();
+        let today = NaiveDate::from_ymd_opt(2023, 1, 19).unwrap();
+        let args = ListArgs {
+            amount: true,
+            day_paid: true,
+            ..list_args()
+        };
+        let lines = list_payments(
+            &args,
+            &mut config.payments,
+            &today,
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(lines, vec!["Gas £20.00, day paid: 6"]);
+        assert_eq!(config.payments[0].category.as_deref(), Some("Energy"));
+    }
+
+    #[test]
+    fn add_existing_bill_is_rejected() {
+        let mut store = MemoryStore::default();
+        store.config.payments = energy_payments();
+        let add = App::try_parse_from(["balance", "add", "Gas", "20.00", "6"]).unwrap();
+
+        assert!(run_ok(&add.command, &mut store).is_err());
+        assert_eq!(store.config.payments.len(), 3);
+    }
+
+    #[test]
+    fn remove_missing_bill_is_rejected() {
+        let mut store = MemoryStore::default();
+        let remove = App::try_parse_from(["balance", "remove", "Gas"]).unwrap();
+
+        assert!(run_ok(&remove.command, &mut store).is_err());
+    }
+
+    #[test]
+    fn piped_output_is_plain() {
+        let money = MoneyFormat {
+            group_separator: Some(','),
+            ..MoneyFormat::default()
+        };
+        let args = compute_args();
+
+        let format = OutputFormat::resolve(args.format, false);
+        let output = compute_balance(&args, &energy_config(), &format.money(&money)).unwrap();
+
+        assert_eq!(format, OutputFormat::Plain);
+        assert_eq!(output.lines, vec!["29.50"]);
+    }
+
+    #[test]
+    fn format_flag_overrides_detection() {
+        assert_eq!(
+            OutputFormat::resolve(Some(OutputFormat::Pretty), false),
+            OutputFormat::Pretty
+        );
+        assert_eq!(
+            OutputFormat::resolve(Some(OutputFormat::Plain), true),
+            OutputFormat::Plain
+        );
+        assert_eq!(OutputFormat::resolve(None, true), OutputFormat::Pretty);
+    }
+
+    #[test]
+    fn daily_spend_to_the_penny() {
+        let args = ComputeArgs {
+            daily: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£29.50", "Safe to spend per day: £1.63"]);
+    }
+
+    #[test]
+    fn daily_spend_to_the_pound() {
+        let args = ComputeArgs {
+            daily: true,
+            round_to: 0,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£29.50", "Safe to spend per day: £1.00"]);
+    }
+
+    #[test]
+    fn round_to_only_accepts_pounds_or_pence() {
+        let result =
+            App::try_parse_from(["balance", "compute", "100", "--daily", "--round-to", "1"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn show_bill_details() {
+        let args = ShowArgs {
+            name: "Gas".to_owned(),
+        };
+
+        let shown = show_entry(&args, &energy_payments(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(shown, "Bill: Gas\nAmount: £20.00\nDay paid: 6");
+    }
+
+    fn duplicated_payments() -> Payments {
+        vec![
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6),
+            Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3),
+            Payment::new("Gas".to_owned(), Decimal::new(2500, 2), 7),
+            Payment::new("Gas".to_owned(), Decimal::new(3000, 2), 8),
+        ]
+    }
+
+    #[test]
+    fn dedupe_keeps_first() {
+        let args = DedupeArgs { keep: Keep::First };
+
+        let (payments, removed) = dedupe_entries(&args, duplicated_payments());
+
+        assert_eq!(removed, 2);
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].amount, Decimal::new(2000, 2));
+    }
+
+    #[test]
+    fn dedupe_keeps_last() {
+        let args = DedupeArgs { keep: Keep::Last };
+
+        let (payments, removed) = dedupe_entries(&args, duplicated_payments());
+
+        assert_eq!(removed, 2);
+        assert_eq!(payments[0].name, "Water");
+        assert_eq!(payments[1].amount, Decimal::new(3000, 2));
+    }
+
+    #[test]
+    fn dedupe_backs_up_first() {
+        let mut store = MemoryStore::default();
+        store.config.payments = duplicated_payments();
+        let dedupe = App::try_parse_from(["balance", "dedupe"]).unwrap();
+
+        run_ok(&dedupe.command, &mut store).unwrap();
+
+        assert_eq!(store.backup.unwrap().payments.len(), 4);
+        assert_eq!(store.config.payments.len(), 2);
+    }
+
+    #[test]
+    fn archived_bills_hidden_by_default() {
+        let args = ArchiveArgs {
+            name: "Gas".to_owned(),
+        };
+        let mut payments = set_archived(&args, true, energy_payments()).unwrap();
+        let today = NaiveDate::from_ymd_opt(2023, 1, 19).unwrap();
+        let money = MoneyFormat::default();
+
+        let hidden = list_payments(
+            &list_args(),
+            &mut payments,
+            &today,
+            &money,
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+        let shown = ListArgs {
+            include_archived: true,
+            ..list_args()
+        };
+        let shown = list_payments(
+            &shown,
+            &mut payments,
+            &today,
+            &money,
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(hidden, vec!["Electric", "Water"]);
+        assert_eq!(shown, vec!["Electric", "Gas", "Water"]);
+    }
+
+    #[test]
+    fn unarchive_restores_bill() {
+        let args = ArchiveArgs {
+            name: "Gas".to_owned(),
+        };
+
+        let payments = set_archived(&args, true, energy_payments()).unwrap();
+        let payments = set_archived(&args, false, payments).unwrap();
+
+        assert!(payments.iter().all(|p| !p.archived));
+    }
+
+    #[test]
+    fn compute_from_balance_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "100.00\nexported 2023-01-31").unwrap();
+        let args = ComputeArgs {
+            balance: None,
+            from_balance_file: Some(file.path().to_owned()),
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£29.50"]);
+    }
+
+    #[test]
+    fn compute_from_invalid_balance_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "£100").unwrap();
+
+        let error = read_balance_file(file.path()).unwrap_err();
+
+        assert!(error.to_string().starts_with("`£100` in "));
+        assert!(read_balance_file(Path::new("/does/not/exist")).is_err());
+    }
+
+    #[test]
+    fn balance_and_balance_file_are_exclusive() {
+        let result =
+            App::try_parse_from(["balance", "compute", "100", "--from-balance-file", "x.txt"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn total_all_bills() {
+        let args = total_args();
+
+        assert_eq!(
+            total_payments(&args, &energy_payments(), &today(), DayStrategy::Clamp),
+            Decimal::new(7050, 2)
+        );
+    }
+
+    #[test]
+    fn total_matching_category() {
+        let args = TotalArgs {
+            category: Some("Energy".to_owned()),
+            ..total_args()
+        };
+
+        assert_eq!(
+            total_payments(&args, &energy_payments(), &today(), DayStrategy::Clamp),
+            Decimal::new(5550, 2)
+        );
+    }
+
+    #[test]
+    fn total_unknown_category() {
+        let args = TotalArgs {
+            category: Some("Travel".to_owned()),
+            ..total_args()
+        };
+
+        let total = total_payments(&args, &energy_payments(), &today(), DayStrategy::Clamp);
+
+        assert_eq!(MoneyFormat::default().format(total), "£0.00");
+    }
+
+    #[test]
+    fn below_warn_threshold() {
+        let args = ComputeArgs {
+            warn_threshold: Some(Decimal::new(50, 0)),
+            fail_on_warn: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£29.50"]);
+        assert_eq!(output.warnings, vec!["warning: balance is below £50.00"]);
+        assert_eq!(args.exit_status(output.balance), LOW_BALANCE_STATUS);
+    }
+
+    #[test]
+    fn above_warn_threshold() {
+        let args = ComputeArgs {
+            warn_threshold: Some(Decimal::new(20, 0)),
+            fail_on_warn: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert!(output.warnings.is_empty());
+        assert_eq!(args.exit_status(output.balance), 0);
+    }
+
+    #[test]
+    fn balance_table_row_per_balance() {
+        let args = ComputeArgs {
+            balance: None,
+            balances: vec![
+                Decimal::new(10000, 2),
+                Decimal::new(20000, 2),
+                Decimal::new(300, 0),
+            ],
+            as_of: NaiveDate::from_ymd_opt(2023, 1, 1),
+            ..compute_args()
+        };
+
+        let rows = balance_table(&args, &energy_config(), &MoneyFormat::default());
+
+        assert_eq!(
+            rows,
+            vec![
+                "£100.00 -> £29.50",
+                "£200.00 -> £129.50",
+                "£300.00 -> £229.50"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_comma_separated_balances() {
+        let app = App::try_parse_from(["balance", "compute", "--table", "100,-20.5"]).unwrap();
+
+        let Commands::Compute(args) = app.command else {
+            panic!("expected compute");
+        };
+        assert_eq!(
+            args.balances,
+            vec![Decimal::new(100, 0), Decimal::new(-205, 1)]
+        );
+    }
+
+    #[test]
+    fn adjust_normalizes_amount() {
+        let args = AdjustArgs {
+            name: Some("Gas".to_owned()),
+            amount: Some(Decimal::new(125, 1)),
+            normalize: true,
+            effective: None,
+            create: false,
+            ..adjust_args()
+        };
+
+        let payments = adjust_entry(&args, energy_payments()).unwrap();
+
+        assert_eq!(payments[0].amount.to_string(), "12.50");
+    }
+
+    #[test]
+    fn compute_sums_accounts() {
+        let app = App::try_parse_from([
+            "balance",
+            "compute",
+            "--accounts",
+            "current=50.00,savings=40.50",
+            "--as-of",
+            "2023-01-01",
+        ])
+        .unwrap();
+        let Commands::Compute(args) = app.command else {
+            panic!("expected compute");
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.balance, Decimal::new(2000, 2));
+    }
+
+    #[test]
+    fn reject_invalid_accounts() {
+        assert_eq!(
+            parse_account("current").unwrap_err(),
+            "`current` isn't of the form name=amount"
+        );
+        assert_eq!(
+            parse_account("=10").unwrap_err(),
+            "`=10` is missing an account name"
+        );
+        assert_eq!(
+            parse_account("current=ten").unwrap_err(),
+            "`ten` isn't a Decimal"
+        );
+        let accounts = vec![
+            ("current".to_owned(), Decimal::ONE),
+            ("current".to_owned(), Decimal::TWO),
+        ];
+        assert_eq!(
+            sum_accounts(&accounts).unwrap_err().to_string(),
+            "account current given more than once"
+        );
+    }
+
+    #[test]
+    fn compute_since_reset_mid_cycle() {
+        let args = ComputeArgs {
+            since_reset: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(
+            output.lines.last().unwrap(),
+            "Day 14 of 31 in the current cycle."
+        );
+    }
+
+    #[test]
+    fn not_found_suggests_closest_name() {
+        let remove = RemoveArgs {
+            name: "Watr".to_owned(),
+        };
+        assert_eq!(
+            remove_entry(&remove, energy_payments())
+                .unwrap_err()
+                .to_string(),
+            "Watr not found, did you mean 'Water'?"
+        );
+
+        let show = ShowArgs {
+            name: "gas".to_owned(),
+        };
+        assert_eq!(
+            show_entry(&show, &energy_payments(), &MoneyFormat::default())
+                .unwrap_err()
+                .to_string(),
+            "gas not found, did you mean 'Gas'?"
+        );
+
+        let adjust = AdjustArgs {
+            name: Some("Broadband".to_owned()),
+            ..adjust_args()
+        };
+        assert_eq!(
+            adjust_entry(&adjust, energy_payments())
+                .unwrap_err()
+                .to_string(),
+            "Broadband not found"
+        );
+    }
+
+    #[test]
+    fn list_due_dates_wrap_to_next_month() {
+        let args = ListArgs {
+            due_dates: true,
+            ..list_args()
+        };
+
+        let lines = list_payments(
+            &args,
+            &mut energy_payments(),
+            &NaiveDate::from_ymd_opt(2023, 1, 6).unwrap(),
+            &MoneyFormat::default(),
+            "%d/%m/%Y",
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Electric, due: 08/01/2023",
+                "Gas, due: 06/01/2023",
+                "Water, due: 03/02/2023"
+            ]
+        );
+    }
+
+    #[test]
+    fn list_pages_with_limit_and_offset() {
+        let page = |limit, offset| {
+            list_names(&ListArgs {
+                limit,
+                offset,
+                ..list_args()
+            })
+            .unwrap()
+        };
+
+        assert_eq!(page(Some(2), 0), vec!["Electric", "Gas"]);
+        assert_eq!(page(Some(2), 2), vec!["Water"]);
+        assert_eq!(page(None, 1), vec!["Gas", "Water"]);
+        assert_eq!(page(Some(0), 0), Vec::<String>::new());
+        assert_eq!(page(Some(10), 5), Vec::<String>::new());
+    }
+
+    #[test]
+    fn list_pages_after_filtering() {
+        let args = ListArgs {
+            category: Some("Energy".to_owned()),
+            limit: Some(1),
+            offset: 1,
+            ..list_args()
+        };
+
+        assert_eq!(list_names(&args).unwrap(), vec!["Gas"]);
+    }
+
+    #[test]
+    fn toggle_twice_restores_state() {
+        let args = ToggleArgs {
+            name: "Gas".to_owned(),
+        };
+
+        let (payments, archived) = toggle_archived(&args, energy_payments()).unwrap();
+        assert!(archived);
+        assert!(payments[0].archived);
+
+        let (payments, archived) = toggle_archived(&args, payments).unwrap();
+        assert!(!archived);
+        assert!(!payments[0].archived);
+    }
+
+    #[test]
+    fn toggle_unknown_bill() {
+        let args = ToggleArgs {
+            name: "Broadband".to_owned(),
+        };
+
+        assert_eq!(
+            toggle_archived(&args, energy_payments())
+                .unwrap_err()
+                .to_string(),
+            "Broadband not found"
+        );
+    }
+
+    #[test]
+    fn not_found_error_as_json() {
+        let args = RemoveArgs {
+            name: "Broadband".to_owned(),
+        };
+
+        let error = remove_entry(&args, energy_payments()).unwrap_err();
+
+        assert_eq!(
+            error::error_json(&error),
+            r#"{"code":"not_found","message":"Broadband not found"}"#
+        );
+    }
+
+    #[test]
+    fn stats_group_totals_sorted_by_total() {
+        let mut payments = energy_payments();
+        payments
+            .push(Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 1).with_category("Home"));
+
+        let lines = stats_report(
+            &StatsArgs {
+                group_total_only: true,
+                include_zero: false,
+                count_by_category: false,
+                filter: NameFilter::default(),
+            },
+            &payments,
+            &today(),
+            &MoneyFormat::default(),
+        );
+
+        assert_eq!(
+            lines,
+            vec!["Home: £300.00", "Energy: £55.50", "Uncategorised: £15.00"]
+        );
+    }
+
+    #[test]
+    fn stats_lists_bills_under_categories() {
+        let lines = stats_report(
+            &StatsArgs {
+                group_total_only: false,
+                include_zero: false,
+                count_by_category: false,
+                filter: NameFilter::default(),
+            },
+            &energy_payments(),
+            &today(),
+            &MoneyFormat::default(),
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                "Energy: £55.50",
+                "  Electric £35.50",
+                "  Gas £20.00",
+                "Uncategorised: £15.00",
+                "  Water £15.00"
+            ]
+        );
+    }
+
+    fn with_zero_bill() -> Payments {
+        let mut payments = energy_payments();
+        payments.push(Payment::new("Placeholder".to_owned(), Decimal::ZERO, 1));
+        payments
+    }
+
+    #[test]
+    fn list_excludes_zero_bills() {
+        let names = |args: &ListArgs| {
+            list_payments(
+                args,
+                &mut with_zero_bill(),
+                &NaiveDate::from_ymd_opt(2023, 1, 19).unwrap(),
+                &MoneyFormat::default(),
+                DEFAULT_DATE_FORMAT,
+                DayStrategy::Clamp,
+            )
+            .unwrap()
+        };
+
+        assert!(names(&list_args()).contains(&"Placeholder".to_owned()));
+        let excluded = ListArgs {
+            exclude_zero: true,
+            ..list_args()
+        };
+        assert_eq!(names(&excluded), vec!["Electric", "Gas", "Water"]);
+
+        let app =
+            App::try_parse_from(["balance", "list", "--exclude-zero", "--include-zero"]).unwrap();
+        let Commands::List(args) = app.command else {
+            panic!("expected list");
+        };
+        assert!(!args.exclude_zero);
+    }
+
+    #[test]
+    fn stats_ignores_zero_bills_unless_included() {
+        let stats = |include_zero| {
+            stats_report(
+                &StatsArgs {
+                    group_total_only: false,
+                    include_zero,
+                    count_by_category: false,
+                    filter: NameFilter::default(),
+                },
+                &with_zero_bill(),
+                &today(),
+                &MoneyFormat::default(),
+            )
+        };
+        let placeholder = "  Placeholder £0.00".to_owned();
+
+        assert!(!stats(false).contains(&placeholder));
+        assert!(stats(true).contains(&placeholder));
+    }
+
+    #[test]
+    fn warn_when_bills_exceed_income() {
+        let income = Some(Decimal::new(10000, 2));
+        let args = AddArgs {
+            name: "Phone".to_owned(),
+            amount: Decimal::new(3000, 2),
+            day_paid: 1,
+            category: None,
+        };
+
+        assert_eq!(
+            income_warning(
+                income,
+                &energy_payments(),
+                &today(),
+                &MoneyFormat::default()
+            ),
+            None
+        );
+        let payments = add_entry(&args, energy_payments()).unwrap();
+        assert_eq!(
+            income_warning(income, &payments, &today(), &MoneyFormat::default()).unwrap(),
+            "warning: monthly bills of £100.50 exceed income of £100.00"
+        );
+        assert_eq!(
+            income_warning(None, &payments, &today(), &MoneyFormat::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn list_sorted_by_cycle_day_not_raw_day() {
+        let mut payments = energy_payments();
+        payments.push(Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 20));
+        payments.push(semi_monthly("Card", 3, 10));
+        let sorted = |sort| {
+            let args = ListArgs {
+                sort,
+                reset_day: 7,
+                ..list_args()
+            };
+            list_payments(
+                &args,
+                &mut payments.clone(),
+                &NaiveDate::from_ymd_opt(2023, 1, 19).unwrap(),
+                &MoneyFormat::default(),
+                DEFAULT_DATE_FORMAT,
+                DayStrategy::Clamp,
+            )
+            .unwrap()
+        };
+
+        let mut by_raw_day = payments.clone();
+        by_raw_day.sort_by_key(|p| p.day_paid);
+        let raw: Vec<&str> = by_raw_day.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(raw, vec!["Card", "Water", "Gas", "Electric", "Rent"]);
+        assert_eq!(
+            sorted(ListSort::Day),
+            vec!["Electric", "Card", "Rent", "Water", "Gas"]
+        );
+    }
+
+    #[test]
+    fn summary_describes_each_change() {
+        let mut after = energy_payments();
+        after[0].amount = Decimal::new(2200, 2);
+        after[1].archived = true;
+        after.remove(2);
+        after.push(Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 1));
+
+        assert_eq!(
+            change_summary(&energy_payments(), &after, &MoneyFormat::default()),
+            vec![
+                "Adjust Gas: £20.00 → £22.00",
+                "Archive Electric",
+                "Remove Water: £15.00",
+                "Add Phone: £10.00"
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_describes_every_field() {
+        let mut after = energy_payments();
+        after[0].weekend_rule = WeekendRule::PrecedingFriday;
+        after[0].share_percent = Some(Decimal::new(50, 0));
+        after[1].end_date = NaiveDate::from_ymd_opt(2023, 6, 30);
+        after[1].frequency = Frequency::SemiMonthly {
+            first: 1,
+            second: 15,
+        };
+
+        assert_eq!(
+            change_summary(&energy_payments(), &after, &MoneyFormat::default()),
+            vec![
+                "Adjust Gas: weekend rule None → PrecedingFriday",
+                "Adjust Gas: share none → 50%",
+                "Adjust Electric: frequency Monthly → SemiMonthly { first: 1, second: 15 }",
+                "Adjust Electric: end date none → 2023-06-30",
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_reports_removed_duplicates() {
+        let before = duplicated_payments();
+        let (after, _) = dedupe_entries(&DedupeArgs { keep: Keep::Last }, before.clone());
+
+        let summary = change_summary(&before, &after, &MoneyFormat::default());
+
+        assert!(
+            summary.iter().all(|line| line.starts_with("Remove ")),
+            "{summary:?}"
+        );
+    }
+
+    #[test]
+    fn declining_confirmation_aborts_write() {
+        let mut store = MemoryStore::default();
+        store.config.payments = energy_payments();
+        let add = App::try_parse_from(["balance", "adjust", "Gas", "-a", "22.00"]).unwrap();
+        let mut asked = vec![];
+
+        run(
+            &add.command,
+            RunOptions::default(),
+            &mut store,
+            &mut |summary| {
+                asked.extend_from_slice(summary);
+                Ok(false)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(asked, vec!["Adjust Gas: £20.00 → £22.00"]);
+        assert_eq!(store.config.payments[0].amount, Decimal::new(2000, 2));
+    }
+
+    #[test]
+    fn list_ndjson_one_object_per_line() {
+        let args = ListArgs {
+            amount: true,
+            category: Some("Energy".to_owned()),
+            format: Some(OutputFormat::Ndjson),
+            ..list_args()
+        };
+
+        let lines = list_names(&args).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        let objects: Vec<serde_json::Value> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            objects,
+            vec![
+                serde_json::json!({"name": "Electric", "amount": "35.50"}),
+                serde_json::json!({"name": "Gas", "amount": "20.00"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn list_follows_the_day_strategy() {
+        let mut payments = energy_payments();
+        payments.push(Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 30));
+        let february = NaiveDate::from_ymd_opt(2023, 2, 10).unwrap();
+        let list = |args: &ListArgs, day_strategy| {
+            list_payments(
+                args,
+                &mut payments.clone(),
+                &february,
+                &MoneyFormat::default(),
+                DEFAULT_DATE_FORMAT,
+                day_strategy,
+            )
+            .unwrap()
+        };
+        let grouped = ListArgs {
+            group_by_day: true,
+            ..list_args()
+        };
+        let sorted = ListArgs {
+            sort: ListSort::Day,
+            reset_day: 4,
+            ..list_args()
+        };
+
+        assert_eq!(
+            list(&grouped, DayStrategy::Skip)[6..],
+            ["Not paid this month:", "  Gym"]
+        );
+        assert_eq!(
+            list(&grouped, DayStrategy::Clamp)[6..],
+            ["Day 28: £30.00", "  Gym"]
+        );
+        assert_eq!(
+            list(&sorted, DayStrategy::Skip),
+            vec!["Gas", "Electric", "Water", "Gym"]
+        );
+        assert_eq!(
+            list(&sorted, DayStrategy::Clamp),
+            vec!["Gas", "Electric", "Gym", "Water"]
+        );
+    }
+
+    #[test]
+    fn list_both_days_of_semi_monthly_bills() {
+        let payments = vec![
+            semi_monthly("Rent", 1, 15),
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6),
+        ];
+        let list = |args: &ListArgs| {
+            list_payments(
+                args,
+                &mut payments.clone(),
+                &today(),
+                &MoneyFormat::default(),
+                DEFAULT_DATE_FORMAT,
+                DayStrategy::Clamp,
+            )
+            .unwrap()
+        };
+        let args = ListArgs {
+            day_paid: true,
+            ..list_args()
+        };
+        let ndjson = ListArgs {
+            format: Some(OutputFormat::Ndjson),
+            ..list_args()
+        };
+
+        assert_eq!(
+            list(&args),
+            vec!["Gas, day_paid: 6", "Rent, day_paid: 1 and 15"]
+        );
+        let objects: Vec<serde_json::Value> = list(&ListArgs {
+            day_paid: true,
+            ..ndjson
+        })
+        .iter()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+        assert_eq!(
+            objects,
+            vec![
+                serde_json::json!({"name": "Gas", "day_paid": 6}),
+                serde_json::json!({"name": "Rent", "day_paid": [1, 15]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn ndjson_rejects_summary_and_compute() {
+        let args = ListArgs {
+            summary: true,
+            format: Some(OutputFormat::Ndjson),
+            ..list_args()
+        };
+        assert!(list_names(&args).is_err());
+
+        assert!(App::try_parse_from(["balance", "compute", "100", "-f", "ndjson"]).is_err());
+    }
+
+    #[test]
+    fn compute_keeps_buffer_aside() {
+        let output = |ignore_buffer| {
+            let args = ComputeArgs {
+                ignore_buffer,
+                ..compute_args()
+            };
+            compute_balance(
+                &args,
+                &Config {
+                    buffer: Decimal::new(2000, 2),
+                    ..energy_config()
+                },
+                &MoneyFormat::default(),
+            )
+            .unwrap()
+        };
+
+        let buffered = output(false);
+        assert_eq!(buffered.balance, Decimal::new(950, 2));
+        assert_eq!(
+            buffered.lines,
+            vec!["£9.50", "Keeping £20.00 aside from £29.50."]
+        );
+
+        let raw = output(true);
+        assert_eq!(raw.balance, Decimal::new(2950, 2));
+        assert_eq!(raw.lines, vec!["£29.50"]);
+    }
+
+    #[test]
+    fn compute_day_strategy_overrides_config() {
+        let config = Config {
+            payments: vec![Payment::new("Rent".to_owned(), Decimal::new(5000, 2), 31)],
+            round_day_strategy: DayStrategy::Skip,
+            ..Config::default()
+        };
+        let balance = |round_day_strategy| {
+            let args = ComputeArgs {
+                reset_day: 1,
+                as_of: NaiveDate::from_ymd_opt(2023, 2, 10),
+                round_day_strategy,
+                ..compute_args()
+            };
+            compute_balance(&args, &config, &MoneyFormat::default())
+                .unwrap()
+                .balance
+        };
+
+        assert_eq!(balance(None), Decimal::new(10000, 2));
+        assert_eq!(balance(Some(DayStrategy::Clamp)), Decimal::new(5000, 2));
+    }
+
+    #[test]
+    fn day_strategy_applies_beyond_compute() {
+        let config = Config {
+            payments: vec![Payment::new("Rent".to_owned(), Decimal::new(5000, 2), 31)],
+            round_day_strategy: DayStrategy::Skip,
+            ..Config::default()
+        };
+        let args = ComputeArgs {
+            reset_day: 1,
+            as_of: NaiveDate::from_ymd_opt(2023, 2, 10),
+            balances: vec![Decimal::new(10000, 2)],
+            ..compute_args()
+        };
+        let total = TotalArgs {
+            future_only: true,
+            reset_day: 1,
+            ..total_args()
+        };
+        let february = NaiveDate::from_ymd_opt(2023, 2, 10).unwrap();
+
+        assert_eq!(
+            balance_table(&args, &config, &MoneyFormat::default()),
+            vec!["£100.00 -> £100.00"]
+        );
+        assert_eq!(
+            total_payments(&total, &config.payments, &february, DayStrategy::Skip),
+            Decimal::ZERO
+        );
+        assert_eq!(
+            total_payments(&total, &config.payments, &february, DayStrategy::Clamp),
+            Decimal::new(5000, 2)
+        );
+    }
+
+    #[test]
+    fn compute_relative_runway() {
+        let args = ComputeArgs {
+            relative: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        // £29.50 left at £70.50 over 31 days is just under 13 days.
+        assert_eq!(output.lines, vec!["~12 days of runway"]);
+    }
+
+    #[test]
+    fn list_with_template() {
+        let app = App::try_parse_from([
+            "balance",
+            "list",
+            "--category",
+            "Energy",
+            "--template",
+            "{name} ({category}) {amount}",
+        ])
+        .unwrap();
+        let Commands::List(args) = app.command else {
+            panic!("expected list");
+        };
+
+        assert_eq!(
+            list_names(&args).unwrap(),
+            vec!["Electric (Energy) £35.50", "Gas (Energy) £20.00"]
+        );
+        assert!(App::try_parse_from(["balance", "list", "--template", "{cost}"]).is_err());
+    }
+
+    #[test]
+    fn stats_counts_bills_by_category() {
+        let mut payments = energy_payments();
+        payments
+            .push(Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 1).with_category("Home"));
+        payments.push(Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 6));
+        payments.push(Payment::new(
+            "Broadband".to_owned(),
+            Decimal::new(2500, 2),
+            9,
+        ));
+
+        let lines = stats_report(
+            &StatsArgs {
+                group_total_only: false,
+                include_zero: false,
+                count_by_category: true,
+                filter: NameFilter::default(),
+            },
+            &payments,
+            &today(),
+            &MoneyFormat::default(),
+        );
+
+        assert_eq!(lines, vec!["Uncategorised: 3", "Energy: 2", "Home: 1"]);
+    }
+
+    #[test]
+    fn list_toml_round_trips_filtered_bills() {
+        let args = ListArgs {
+            category: Some("Energy".to_owned()),
+            format: Some(OutputFormat::Toml),
+            ..list_args()
+        };
+
+        let toml = list_names(&args).unwrap().join("\n");
+        let document: toml_edit::DocumentMut = toml.parse().unwrap();
+
+        let bills: Vec<(&str, Decimal)> = document["payments"]
+            .as_array_of_tables()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                let amount = t["amount"].as_str().unwrap().parse().unwrap();
+                (t["name"].as_str().unwrap(), amount)
+            })
+            .collect();
+        assert_eq!(
+            bills,
+            vec![
+                ("Electric", Decimal::new(3550, 2)),
+                ("Gas", Decimal::new(2000, 2))
+            ]
+        );
+        assert!(toml.contains("amount = \"35.50\""));
+    }
+
+    #[test]
+    fn compute_reports_zero_date() {
+        let lines = |zero_date_months| {
+            let args = ComputeArgs {
+                zero_date: true,
+                zero_date_months,
+                ..compute_args()
+            };
+            compute_balance(&args, &energy_config(), &MoneyFormat::default())
+                .unwrap()
+                .lines
+        };
+
+        assert_eq!(
+            lines(12)[1],
+            "Without income the balance goes negative on 2023-03-06."
+        );
+        assert_eq!(
+            lines(1)[1],
+            "Without income the balance never goes negative within 1 month."
+        );
+    }
+
+    #[test]
+    fn name_contains_filters_list_total_and_stats() {
+        let filter = NameFilter {
+            name_contains: Some("ELEC".to_owned()),
+        };
+
+        let list = ListArgs {
+            filter: filter.clone(),
+            ..list_args()
+        };
+        assert_eq!(list_names(&list).unwrap(), vec!["Electric"]);
+
+        let total = TotalArgs {
+            filter: filter.clone(),
+            ..total_args()
+        };
+        assert_eq!(
+            total_payments(&total, &energy_payments(), &today(), DayStrategy::Clamp),
+            Decimal::new(3550, 2)
+        );
+
+        let stats = StatsArgs {
+            group_total_only: true,
+            include_zero: false,
+            count_by_category: false,
+            filter,
+        };
+        assert_eq!(
+            stats_report(
+                &stats,
+                &energy_payments(),
+                &today(),
+                &MoneyFormat::default()
+            ),
+            vec!["Energy: £35.50"]
+        );
+    }
+
+    #[test]
+    fn doctor_fixes_long_amounts() {
+        let mut store = MemoryStore {
+            config: Config {
+                payments: vec![Payment::new("Gas".to_owned(), Decimal::new(204550, 4), 6)],
+                ..Config::default()
+            },
+            ..MemoryStore::default()
+        };
+
+        let check = App::try_parse_from(["balance", "doctor", "--precision-check"]).unwrap();
+        assert_eq!(
+            run_ok(&check.command, &mut store).unwrap(),
+            PROBLEMS_FOUND_STATUS
+        );
+        assert_eq!(store.config.payments[0].amount.scale(), 4);
+
+        let fix = App::try_parse_from(["balance", "doctor", "--fix"]).unwrap();
+        assert_eq!(run_ok(&fix.command, &mut store).unwrap(), 0);
+        assert_eq!(store.config.payments[0].amount.to_string(), "20.46");
+    }
+
+    #[test]
+    fn description_survives_adjust() {
+        let contents = "payments:
+- name: Gas
+  amount: '20.00'
+  day_paid: 6
+  description: Fixed until March
+";
+        let mut store = MemoryStore {
+            config: serde_yaml::from_str(contents).unwrap(),
+            ..MemoryStore::default()
+        };
+        let app = App::try_parse_from(["balance", "adjust", "Gas", "-a", "22.00"]).unwrap();
+
+        run_ok(&app.command, &mut store).unwrap();
+
+        let stored = serde_yaml::to_string(&store.config).unwrap();
+        assert!(stored.contains("amount: '22.00'"));
+        assert!(stored.contains("description: Fixed until March"));
+    }
+
+    #[test]
+    fn compute_as_percentage_of_income() {
+        let output = |income| {
+            let args = ComputeArgs {
+                as_percentage_of: Some(income),
+                ..compute_args()
+            };
+            compute_balance(&args, &energy_config(), &MoneyFormat::default())
+        };
+
+        assert_eq!(
+            output(Decimal::new(50000, 2)).unwrap().lines,
+            vec!["£29.50", "5.9% of £500.00"]
+        );
+        assert!(output(Decimal::ZERO).is_err());
+    }
+
+    #[test]
+    fn adjust_effective_records_change() {
+        let mut store = MemoryStore {
+            config: energy_config(),
+            ..MemoryStore::default()
+        };
+        let app = App::try_parse_from([
+            "balance",
+            "adjust",
+            "Gas",
+            "-a",
+            "30.00",
+            "--effective",
+            "2023-01-25",
+        ])
+        .unwrap();
+
+        run_ok(&app.command, &mut store).unwrap();
+
+        assert_eq!(
+            store.config.payments[0].change,
+            Some(AmountChange {
+                previous: Decimal::new(2000, 2),
+                effective: NaiveDate::from_ymd_opt(2023, 1, 25).unwrap(),
+            })
+        );
+        assert!(
+            App::try_parse_from(["balance", "adjust", "Gas", "--effective", "2023-01-25"]).is_err()
+        );
+    }
+
+    #[test]
+    fn due_groups_bills_by_week() {
+        let mut payments = energy_payments();
+        payments.push(Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 20));
+        let args = DueArgs {
+            sort_by_due_date: true,
+            as_of: None,
+        };
+
+        // 2023-02-01 is a Wednesday, so this week runs until Sunday the 5th.
+        let lines = due_report(
+            &args,
+            &payments,
+            &NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "This week:",
+                "  Water £15.00, due: 2023-02-03",
+                "Next week:",
+                "  Gas £20.00, due: 2023-02-06",
+                "  Electric £35.50, due: 2023-02-08",
+                "Later:",
+                "  Rent £300.00, due: 2023-02-20",
+            ]
+        );
+    }
+
+    fn their_payments() -> Payments {
+        vec![
+            Payment::new("Gas".to_owned(), Decimal::new(2500, 2), 7),
+            Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 1),
+        ]
+    }
+
+    fn merged_bills(payments: &Payments) -> Vec<(&str, Decimal)> {
+        payments
+            .iter()
+            .map(|p| (p.name.as_str(), p.amount))
+            .collect()
+    }
+
+    #[test]
+    fn merge_keeping_mine() {
+        let merged =
+            merge_payments(MergeStrategy::KeepMine, energy_payments(), their_payments()).unwrap();
+
+        assert_eq!(
+            merged_bills(&merged),
+            vec![
+                ("Gas", Decimal::new(2000, 2)),
+                ("Electric", Decimal::new(3550, 2)),
+                ("Water", Decimal::new(1500, 2)),
+                ("Rent", Decimal::new(30000, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_keeping_theirs() {
+        let merged = merge_payments(
+            MergeStrategy::KeepTheirs,
+            energy_payments(),
+            their_payments(),
+        )
+        .unwrap();
+
+        assert_eq!(merged[0].amount, Decimal::new(2500, 2));
+        assert_eq!(merged[0].day_paid, 7);
+        assert_eq!(merged.len(), 4);
+    }
+
+    #[test]
+    fn merge_errors_on_collision() {
+        let error =
+            merge_payments(MergeStrategy::Error, energy_payments(), their_payments()).unwrap_err();
+        assert_eq!(error.to_string(), "both configs have Gas");
+
+        let merged = merge_payments(
+            MergeStrategy::Error,
+            energy_payments(),
+            their_payments()[1..].to_vec(),
+        )
+        .unwrap();
+        assert_eq!(merged.len(), 4);
+    }
+
+    #[test]
+    fn merge_backs_up_before_storing() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"payments:\n- name: Rent\n  amount: '300.00'\n  day_paid: 1\n")
+            .unwrap();
+        let mut store = MemoryStore {
+            config: energy_config(),
+            ..MemoryStore::default()
+        };
+        let command = Commands::Merge(MergeArgs {
+            path: file.path().to_owned(),
+            strategy: MergeStrategy::Error,
+        });
+
+        run_ok(&command, &mut store).unwrap();
+
+        assert_eq!(store.backup.unwrap().payments.len(), 3);
+        assert_eq!(store.config.payments.len(), 4);
+    }
+
+    #[test]
+    fn declined_merge_is_not_backed_up_or_stored() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"payments:\n- name: Rent\n  amount: '300.00'\n  day_paid: 1\n")
+            .unwrap();
+        let mut store = MemoryStore {
+            config: energy_config(),
+            ..MemoryStore::default()
+        };
+        let command = Commands::Merge(MergeArgs {
+            path: file.path().to_owned(),
+            strategy: MergeStrategy::Error,
+        });
+
+        run(&command, RunOptions::default(), &mut store, &mut |_| {
+            Ok(false)
+        })
+        .unwrap();
+
+        assert!(store.backup.is_none());
+        assert_eq!(store.stores, 0);
+    }
+
+    #[test]
+    fn list_tsv_columns() {
+        let args = ListArgs {
+            amount: true,
+            day_paid: true,
+            tsv: true,
+            ..list_args()
+        };
+
+        assert_eq!(
+            list_names(&args).unwrap(),
+            vec!["Electric\t35.50\t8", "Gas\t20.00\t6", "Water\t15.00\t3"]
+        );
+
+        let names_only = ListArgs {
+            tsv: true,
+            ..list_args()
+        };
+        assert_eq!(list_names(&names_only).unwrap()[0], "Electric");
+    }
+
+    #[test]
+    fn due_annotates_weekend_dates() {
+        let payments = vec![
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 4),
+            Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3),
+        ];
+        let args = DueArgs {
+            sort_by_due_date: false,
+            as_of: None,
+        };
+
+        let lines = due_report(
+            &args,
+            &payments,
+            &NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Gas £20.00, due: 2023-02-04 (weekend)",
+                "Water £15.00, due: 2023-02-03"
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_env_lines() {
+        let args = ComputeArgs {
+            format: Some(OutputFormat::Env),
+            date_format: Some("%d %b %Y".to_owned()),
+            ..compute_args()
+        };
+
+        let output =
+            compute_balance(&args, &energy_config(), &MoneyFormat::default().plain()).unwrap();
+
+        assert_eq!(
+            output.lines,
+            vec![
+                "BALANCE_REMAINING=29.50",
+                "BALANCE_RESET_DAY=18",
+                "BALANCE_DATE='31 Jan 2023'"
+            ]
+        );
+        assert_eq!(env_line("NAME", "it's"), "NAME='it'\\''s'");
+        assert!(App::try_parse_from(["balance", "list", "-f", "env"]).is_err());
+    }
+
+    #[test]
+    fn exact_total_keeps_every_decimal_place() {
+        let payments = vec![
+            Payment::new("Gas".to_owned(), Decimal::new(20455, 3), 6),
+            Payment::new("Electric".to_owned(), Decimal::new(355, 1), 8),
+            Payment::new("Water".to_owned(), Decimal::new(150000, 4), 3),
+        ];
+        let args = TotalArgs {
+            exact: true,
+            ..total_args()
+        };
+
+        assert_eq!(
+            total_lines(
+                &args,
+                &payments,
+                &today(),
+                DayStrategy::Clamp,
+                &MoneyFormat::default(),
+            ),
+            vec![
+                "Exact total: 70.9550 (4 decimal places)",
+                "Shown as: £70.96"
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_compact_line() {
+        let args = ComputeArgs {
+            compact: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£29.50 (3 bills left, reset 18th)"]);
+    }
+
+    #[test]
+    fn blackout_days_reject_add_and_adjust() {
+        let mut store = MemoryStore {
+            config: Config {
+                blackout_days: vec![1],
+                ..energy_config()
+            },
+            ..MemoryStore::default()
+        };
+        let mut run_args = |args: &[&str]| {
+            let app = App::try_parse_from(args).unwrap();
+            run_ok(&app.command, &mut store)
+        };
+
+        let error = run_args(&["balance", "add", "Rent", "300", "1"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "day 1 is a blackout day, choose another day to pay the bill on"
+        );
+        assert!(run_args(&["balance", "adjust", "Gas", "-d", "1"]).is_err());
+
+        run_args(&["balance", "add", "Rent", "300", "2"]).unwrap();
+        run_args(&["balance", "adjust", "Gas", "-d", "3"]).unwrap();
+        assert_eq!(store.config.payments.len(), 4);
+        assert_eq!(store.config.payments[0].day_paid, 3);
+    }
+
+    #[test]
+    fn reset_today_uses_the_current_day() {
+        let args = |as_of| ComputeArgs {
+            reset_today: true,
+            as_of: NaiveDate::from_ymd_opt(2023, 1, as_of),
+            ..compute_args()
+        };
+
+        assert_eq!(args(5).reset_day(), 5);
+        assert_eq!(args(31).reset_day(), 28);
+        assert!(
+            App::try_parse_from(["balance", "compute", "100", "-r", "5", "--reset-today"]).is_err()
+        );
+    }
+
+    #[test]
+    fn amounts_may_start_with_a_currency_symbol() {
+        assert_eq!(amount_validation("£20.00"), Ok(Decimal::new(2000, 2)));
+        assert_eq!(amount_validation("£ 20.00"), Ok(Decimal::new(2000, 2)));
+        assert_eq!(amount_validation("NZ$5"), Ok(Decimal::new(5, 0)));
+        assert_eq!(amount_validation("20"), Ok(Decimal::new(20, 0)));
+        assert_eq!(
+            amount_validation("abc"),
+            Err("`abc` isn't a Decimal".to_owned())
+        );
+        assert!(amount_validation("££20").is_err());
+    }
+
+    #[test]
+    fn adjust_create_upserts_bills() {
+        let adjust = |args: &[&str]| {
+            let app = App::try_parse_from(args).unwrap();
+            let Commands::Adjust(args) = app.command else {
+                panic!("expected adjust");
+            };
+            adjust_entry(&args, energy_payments())
+        };
+
+        let adjusted = adjust(&["balance", "adjust", "Gas", "-a", "25", "--create"]).unwrap();
+        assert_eq!(adjusted.len(), 3);
+        assert_eq!(adjusted[0].amount, Decimal::new(25, 0));
+
+        let created = adjust(&[
+            "balance", "adjust", "Rent", "-a", "300", "-d", "1", "--create",
+        ])
+        .unwrap();
+        assert_eq!(created.len(), 4);
+        assert_eq!(created[3].name, "Rent");
+        assert_eq!(created[3].day_paid, 1);
+
+        let error = adjust(&["balance", "adjust", "Rent", "-a", "300", "--create"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Rent doesn't exist, so --amount and --day-paid are needed to create it"
+        );
+        assert!(adjust(&["balance", "adjust", "Rent", "-a", "300"]).is_err());
+    }
+
+    #[test]
+    fn compute_highlights_balance_band() {
+        let config = Config {
+            color_thresholds: Some(color::ColorThresholds {
+                low: Decimal::new(20, 0),
+                high: Decimal::new(100, 0),
+            }),
+            ..energy_config()
+        };
+        let args = ComputeArgs {
+            explain: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &config, &MoneyFormat::default()).unwrap();
+        let (index, band) = output.highlight.unwrap();
+
+        assert_eq!(output.lines[index], "£29.50");
+        assert_eq!(band, ColorBand::Amber);
+        assert_eq!(
+            compute_balance(&args, &energy_config(), &MoneyFormat::default())
+                .unwrap()
+                .highlight,
+            None
+        );
+    }
+
+    #[test]
+    fn list_and_total_skip_ended_bills() {
+        let mut payments = energy_payments();
+        payments[0].end_date = NaiveDate::from_ymd_opt(2023, 1, 1);
+        let total = total_args();
+
+        let lines = list_payments(
+            &list_args(),
+            &mut payments,
+            &today(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(lines, vec!["Electric", "Water"]);
+        assert_eq!(
+            total_payments(&total, &payments, &today(), DayStrategy::Clamp),
+            Decimal::new(5050, 2)
+        );
+    }
+
+    #[test]
+    fn delimiter_must_be_a_single_character() {
+        assert_eq!(delimiter_validation(";"), Ok(';'));
+        assert_eq!(
+            delimiter_validation(";;"),
+            Err("`;;` isn't a single character".to_owned())
+        );
+        assert!(delimiter_validation("").is_err());
+        assert!(delimiter_validation("\"").is_err());
+    }
+
+    #[test]
+    fn compute_adds_back_leftover_bills() {
+        let args = ComputeArgs {
+            add_back: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(output.lines, vec!["£170.50"]);
+    }
+
+    #[test]
+    fn reminders_written_for_bills_due_soon() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = RemindArgs {
+            path: dir.path().join("reminders.json"),
+            within: 3,
+            as_of: None,
+        };
+
+        write_reminders(
+            &args,
+            &energy_payments(),
+            &NaiveDate::from_ymd_opt(2023, 2, 5).unwrap(),
+        )
+        .unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&args.path).unwrap()).unwrap();
+
+        assert_eq!(
+            written,
+            serde_json::json!([
+                { "name": "Gas", "amount": "20.00", "due": "2023-02-06" },
+                { "name": "Electric", "amount": "35.50", "due": "2023-02-08" },
+            ])
+        );
+    }
+
+    #[test]
+    fn list_sorted_by_category() {
+        let args = ListArgs {
+            sort: ListSort::Category,
+            ..list_args()
+        };
+        let mut payments = energy_payments();
+        payments.push(Payment::new(
+            "Broadband".to_owned(),
+            Decimal::new(3000, 2),
+            1,
+        ));
+        payments
+            .push(Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 1).with_category("Home"));
+        payments.push(
+            Payment::new("Council Tax".to_owned(), Decimal::new(9000, 2), 1).with_category("Home"),
+        );
+
+        let lines = list_payments(
+            &args,
+            &mut payments,
+            &today(),
+            &MoneyFormat::default(),
+            DEFAULT_DATE_FORMAT,
+            DayStrategy::Clamp,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Electric",
+                "Gas",
+                "Council Tax",
+                "Rent",
+                "Broadband",
+                "Water"
+            ]
+        );
+    }
+
+    #[test]
+    fn effective_settings_report_their_source() {
+        let app = App::try_parse_from(["balance", "config", "-r", "25"]).unwrap();
+        let Commands::Config(args) = app.command else {
+            panic!("expected config");
+        };
+        let config = Config {
+            buffer: Decimal::new(5000, 2),
+            ..energy_config()
+        };
+
+        let settings = effective_settings(&args, &config, None, true);
+        let setting = |name: &str| {
+            settings
+                .iter()
+                .find(|(n, _, _)| *n == name)
+                .map(|(_, value, source)| (value.as_str(), *source))
+                .unwrap()
+        };
+
+        assert_eq!(setting("reset_day"), ("25", SettingSource::Flag));
+        assert_eq!(setting("buffer"), ("£50.00", SettingSource::Config));
+        assert_eq!(setting("currency"), ("£", SettingSource::Default));
+        assert_eq!(setting("color"), ("off", SettingSource::Env));
+        assert_eq!(SettingSource::Flag.to_string(), "flag");
+    }
+
+    #[test]
+    fn effective_settings_report_locale_overrides() {
+        let app = App::try_parse_from(["balance", "config"]).unwrap();
+        let Commands::Config(args) = app.command else {
+            panic!("expected config");
+        };
+        let german = Locale::parse("de-DE").ok();
+
+        let settings = effective_settings(&args, &energy_config(), german, false);
+        let setting = |name: &str| {
+            settings
+                .iter()
+                .find(|(n, _, _)| *n == name)
+                .map(|(_, value, source)| (value.as_str(), *source))
+                .unwrap()
+        };
+
+        assert_eq!(setting("date_format"), ("%d.%m.%Y", SettingSource::Locale));
+        assert_eq!(
+            setting("currency_position"),
+            ("Suffix", SettingSource::Locale)
+        );
+        assert_eq!(setting("currency"), ("£", SettingSource::Default));
+        assert_eq!(setting("buffer"), ("0,00£", SettingSource::Default));
+    }
+
+    #[test]
+    fn future_only_total_matches_leftover_deductions() {
+        let args = TotalArgs {
+            future_only: true,
+            ..total_args()
+        };
+        let today = NaiveDate::from_ymd_opt(2023, 2, 7).unwrap();
+        let payment_manager = PaymentManager::new(
+            Decimal::new(10000, 2),
+            18,
+            energy_payments(),
+            DayStrategy::Clamp,
+        );
+        let leftover: Decimal = payment_manager
+            .cycle_payments(&today)
+            .iter()
+            .map(|p| p.amount)
+            .sum();
+
+        let total = total_payments(&args, &energy_payments(), &today, DayStrategy::Clamp);
+
+        assert_eq!(total, leftover);
+        assert_eq!(total, Decimal::new(3550, 2));
+    }
+
+    #[test]
+    fn rounding_report_notes_rounding() {
+        let money = MoneyFormat::default();
+
+        assert_eq!(
+            rounding_report(Decimal::new(29_505, 3), &money),
+            vec![
+                "Exact balance: 29.505",
+                "Shown as: £29.51 (rounded by 0.005)"
+            ]
+        );
+        assert_eq!(
+            rounding_report(Decimal::new(2950, 2), &money),
+            vec![
+                "Exact balance: 29.50",
+                "Shown as: £29.50 (no rounding needed)"
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_rounding_report_follows_balance() {
+        let args = ComputeArgs {
+            rounding_report: true,
+            ..compute_args()
+        };
+
+        let output = compute_balance(&args, &energy_config(), &MoneyFormat::default()).unwrap();
+
+        assert_eq!(
+            output.lines,
+            vec![
+                "£29.50",
+                "Exact balance: 29.50",
+                "Shown as: £29.50 (no rounding needed)"
+            ]
+        );
+    }
+
+    #[test]
+    fn forecast_from_start_or_current_balance() {
+        let forecast = |start_balance| {
+            let args = ForecastArgs {
+                balance: Decimal::new(10000, 2),
+                start_balance,
+                current_balance: !start_balance,
+                reset_day: 18,
+                income: Some(Decimal::new(8000, 2)),
+                cycles: 1,
+                as_of: NaiveDate::from_ymd_opt(2023, 2, 7),
+                group_by: None,
+            };
+            forecast_balances(
+                &args,
+                energy_payments(),
+                DayStrategy::Clamp,
+                &MoneyFormat::default(),
+            )
+        };
+
+        assert_eq!(
+            forecast(false),
+            vec!["This cycle: £64.50", "Cycle +1: £74.00"]
+        );
+        assert_eq!(
+            forecast(true),
+            vec!["This cycle: £29.50", "Cycle +1: £39.00"]
+        );
+    }
+
+    #[test]
+    fn if_changed_skips_storing_a_no_op_adjust() {
+        let adjust =
+            App::try_parse_from(["balance", "adjust", "Gas", "--amount", "20.00"]).unwrap();
+        let changed =
+            App::try_parse_from(["balance", "adjust", "Gas", "--amount", "25.00"]).unwrap();
+        let options = RunOptions {
+            if_changed: true,
+            ..RunOptions::default()
+        };
+        let mut store = MemoryStore {
+            config: energy_config(),
+            ..MemoryStore::default()
+        };
+
+        run(&adjust.command, options, &mut store, &mut |_| Ok(true)).unwrap();
+        assert_eq!(store.stores, 0);
+
+        run_ok(&adjust.command, &mut store).unwrap();
+        assert_eq!(store.stores, 1);
+
+        run(&changed.command, options, &mut store, &mut |_| Ok(true)).unwrap();
+        assert_eq!(store.stores, 2);
+        assert_eq!(store.config.payments[0].amount, Decimal::new(2500, 2));
+    }
+
+    #[test]
+    fn import_pasted_table_from_clipboard() {
+        let args = ImportArgs {
+            path: None,
+            from_clipboard: true,
+            round: false,
+            merge: false,
+            format: ImportFormat::Csv,
+            dry_run: false,
+            delimiter: ',',
+        };
+
+        let contents = import_contents(&args, || {
+            Ok("name\tamount\tday_paid\nGas\t20.00\t6\nPhone\t10.00\t31\n".to_owned())
+        })
+        .unwrap();
+        let error = import_entries(&args, &contents, vec![]).unwrap_err();
+        let payments = import_entries(&args, &contents.replace("31", "28"), vec![]).unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid rows found:\nline 3: days paid not in range 1-28"
+        );
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].name, "Gas");
+        assert_eq!(payments[1].day_paid, 28);
+    }
+
+    #[test]
+    fn import_empty_clipboard_fails() {
+        let args = ImportArgs {
+            path: None,
+            from_clipboard: true,
+            round: false,
+            merge: false,
+            format: ImportFormat::Csv,
+            dry_run: false,
+            delimiter: ',',
+        };
+
+        let error = import_contents(&args, || Ok(" \n".to_owned())).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "the clipboard is empty, copy the bills to import first"
+        );
+    }
+
+    #[test]
+    fn net_monthly_shows_surplus_and_deficit() {
+        let money = MoneyFormat::default();
+
+        assert_eq!(
+            net_monthly(
+                Some(Decimal::new(10000, 2)),
+                &energy_payments(),
+                &today(),
+                &money
+            )
+            .unwrap(),
+            "Net monthly: £29.50 (29.5% of income)"
+        );
+        assert_eq!(
+            net_monthly(
+                Some(Decimal::new(5000, 2)),
+                &energy_payments(),
+                &today(),
+                &money
+            )
+            .unwrap(),
+            "Net monthly: £-20.50 (-41.0% of income)"
+        );
+        assert_eq!(
+            net_monthly(None, &energy_payments(), &today(), &money),
+            None
+        );
+    }
+
+    #[test]
+    fn list_only_names() {
+        let args = ListArgs {
+            amount: true,
+            day_paid: true,
+            only_name: true,
+            ..list_args()
+        };
+
+        assert_eq!(list_names(&args).unwrap(), vec!["Electric", "Gas", "Water"]);
+    }
+
+    #[test]
+    fn stats_leave_out_ended_bills() {
+        let mut payments = energy_payments();
+        payments.push(Payment {
+            end_date: NaiveDate::from_ymd_opt(2022, 12, 31),
+            ..Payment::new("Heating oil".to_owned(), Decimal::new(5000, 2), 10)
+                .with_category("Energy")
+        });
+        let args = StatsArgs {
+            group_total_only: true,
+            include_zero: false,
+            count_by_category: false,
+            filter: NameFilter::default(),
+        };
+        let money = MoneyFormat::default();
+
+        assert_eq!(
+            stats_report(&args, &payments, &today(), &money),
+            vec!["Energy: £55.50", "Uncategorised: £15.00"]
+        );
+        assert_eq!(
+            net_monthly(Some(Decimal::new(10000, 2)), &payments, &today(), &money).unwrap(),
+            "Net monthly: £29.50 (29.5% of income)"
+        );
+    }
+
+    #[test]
+    fn only_commands_changing_the_config_lock_it() {
+        let mut store = MemoryStore {
+            config: energy_config(),
+            ..MemoryStore::default()
+        };
+        let locks = |store: &mut MemoryStore, args: &[&str]| {
+            let app = App::try_parse_from(args).unwrap();
+            store.locks.set(0);
+            run_ok(&app.command, store).unwrap();
+            store.locks.get()
+        };
+
+        assert_eq!(locks(&mut store, &["balance", "list"]), 0);
+        assert_eq!(locks(&mut store, &["balance", "stats"]), 0);
+        assert_eq!(locks(&mut store, &["balance", "compute", "100"]), 0);
+        assert_eq!(
+            locks(&mut store, &["balance", "adjust", "Gas", "-a", "25"]),
+            1
+        );
+    }
+
+    #[test]
+    fn compute_cache_is_best_effort() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MemoryStore {
+            config: energy_config(),
+            modified: Some(std::time::SystemTime::UNIX_EPOCH),
+            cache_path: Some(dir.path().join("missing").join("compute.cache")),
+            ..MemoryStore::default()
+        };
+        let app = App::try_parse_from(["balance", "compute", "100"]).unwrap();
+
+        let status = run_ok(&app.command, &mut store);
+
+        assert_eq!(status.unwrap(), 0);
+    }
+
+    #[test]
+    fn compute_cache_key_includes_strictness() {
+        let key = |strict| {
+            let store = MemoryStore {
+                modified: Some(std::time::SystemTime::UNIX_EPOCH),
+                strict,
+                ..MemoryStore::default()
+            };
+            compute_cache_key(&compute_args(), None, &store, false).unwrap()
+        };
+
+        assert_ne!(key(false), key(true));
     }
 }
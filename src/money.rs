@@ -0,0 +1,336 @@
+use std::{
+    fmt::{self, Display},
+    iter::Sum,
+    ops::{Add, Neg, Sub},
+};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    #[default]
+    HalfUp,
+    HalfEven,
+    Down,
+}
+
+impl RoundingMode {
+    pub const fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::Down => RoundingStrategy::ToZero,
+        }
+    }
+
+    pub fn round(self, amount: Decimal) -> Decimal {
+        amount.round_dp_with_strategy(2, self.strategy())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Position {
+    #[default]
+    Prefix,
+    Suffix,
+}
+
+pub const DEFAULT_CURRENCY_SYMBOL: &str = "£";
+pub const DEFAULT_GROUP_SEPARATOR: char = ',';
+pub const DEFAULT_DECIMAL_SEPARATOR: char = '.';
+
+/// Symbols for common ISO 4217 currency codes.
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("AUD", "A$"),
+    ("CAD", "C$"),
+    ("CHF", "CHF"),
+    ("CNY", "¥"),
+    ("EUR", "€"),
+    ("GBP", "£"),
+    ("INR", "₹"),
+    ("JPY", "¥"),
+    ("NZD", "NZ$"),
+    ("USD", "$"),
+];
+
+/// Symbol for a currency code, ignoring case, or the code itself if it isn't known.
+pub fn currency_symbol(code: &str) -> String {
+    let code = code.trim().to_uppercase();
+    CURRENCY_SYMBOLS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, symbol)| (*symbol).to_owned())
+}
+
+/// `s` without a leading currency symbol and the whitespace around it, so amounts pasted with
+/// their symbol, such as `£20.00`, can be parsed.
+///
+/// The `configured` symbol is stripped, as well as every symbol in the known currencies, so an
+/// amount copied from elsewhere can still be given.
+pub fn strip_currency_symbol<'a>(s: &'a str, configured: Option<&str>) -> &'a str {
+    let s = s.trim_start();
+    CURRENCY_SYMBOLS
+        .iter()
+        .map(|(_, symbol)| *symbol)
+        .chain(configured.filter(|symbol| !symbol.is_empty()))
+        .filter(|symbol| s.starts_with(symbol))
+        .max_by_key(|symbol| symbol.len())
+        .map_or(s, |symbol| s[symbol.len()..].trim_start())
+}
+
+/// An amount of money as a whole number of pence, so bills can be summed without gaining or
+/// losing fractions of a penny.
+///
+/// Amounts are read from the config and command line as [`Decimal`] pounds and converted with
+/// [`Pence::from_pounds`] before they are added up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pence(pub i64);
+
+impl Pence {
+    /// `amount` to the nearest penny, with halves rounded away from zero. Amounts too large to
+    /// hold are capped.
+    pub fn from_pounds(amount: Decimal) -> Self {
+        let pence = amount.checked_mul(Decimal::ONE_HUNDRED).and_then(|p| {
+            p.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+                .to_i64()
+        });
+        let cap = if amount.is_sign_negative() {
+            i64::MIN
+        } else {
+            i64::MAX
+        };
+        Self(pence.unwrap_or(cap))
+    }
+}
+
+impl From<Pence> for Decimal {
+    fn from(pence: Pence) -> Self {
+        Self::new(pence.0, 2)
+    }
+}
+
+impl Add for Pence {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Pence {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Pence {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Sum for Pence {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Shown in pounds with two decimal places, such as `12.34`.
+impl Display for Pence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Decimal::from(*self))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MoneyFormat {
+    pub rounding: RoundingMode,
+    pub symbol: String,
+    pub position: Position,
+    /// Separator placed between each group of three digits, if grouping is enabled.
+    pub group_separator: Option<char>,
+    pub decimal_separator: char,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        Self {
+            rounding: RoundingMode::default(),
+            symbol: DEFAULT_CURRENCY_SYMBOL.to_owned(),
+            position: Position::default(),
+            group_separator: None,
+            decimal_separator: DEFAULT_DECIMAL_SEPARATOR,
+        }
+    }
+}
+
+fn group_digits(digits: &str, separator: char) -> String {
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl MoneyFormat {
+    /// The same rounding without any symbol or grouping, for output read by other programs.
+    pub fn plain(&self) -> Self {
+        Self {
+            rounding: self.rounding,
+            symbol: String::new(),
+            ..Self::default()
+        }
+    }
+
+    pub fn format_number(&self, amount: Decimal) -> String {
+        let amount = self.rounding.round(amount);
+        let sign = if amount.is_sign_negative() && !amount.is_zero() {
+            "-"
+        } else {
+            ""
+        };
+        let plain = format!("{:.2}", amount.abs());
+        let (units, pence) = plain.split_once('.').unwrap_or((&plain, "00"));
+        let units = self
+            .group_separator
+            .map_or_else(|| units.to_owned(), |s| group_digits(units, s));
+
+        format!("{sign}{units}{}{pence}", self.decimal_separator)
+    }
+
+    pub fn format(&self, amount: Decimal) -> String {
+        let number = self.format_number(amount);
+        match self.position {
+            Position::Prefix => format!("{}{number}", self.symbol),
+            Position::Suffix => format!("{number}{}", self.symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ops::Neg, str::FromStr};
+
+    use super::*;
+
+    fn format_with(rounding: RoundingMode, amount: &str) -> String {
+        MoneyFormat {
+            rounding,
+            ..MoneyFormat::default()
+        }
+        .format(Decimal::from_str(amount).unwrap())
+    }
+
+    #[test]
+    fn rounding_modes_differ_on_midpoint() {
+        assert_eq!(format_with(RoundingMode::HalfUp, "0.125"), "£0.13");
+        assert_eq!(format_with(RoundingMode::HalfEven, "0.125"), "£0.12");
+        assert_eq!(format_with(RoundingMode::Down, "0.125"), "£0.12");
+    }
+
+    #[test]
+    fn rounding_down_truncates() {
+        assert_eq!(format_with(RoundingMode::HalfEven, "0.135"), "£0.14");
+        assert_eq!(format_with(RoundingMode::Down, "0.135"), "£0.13");
+    }
+
+    #[test]
+    fn whole_amounts_are_padded() {
+        assert_eq!(format_with(RoundingMode::HalfUp, "20"), "£20.00");
+    }
+
+    #[test]
+    fn symbol_positions() {
+        let amount = Decimal::new(1200, 2);
+        let prefix = MoneyFormat {
+            symbol: "€".to_owned(),
+            ..MoneyFormat::default()
+        };
+        let suffix = MoneyFormat {
+            position: Position::Suffix,
+            ..prefix.clone()
+        };
+
+        assert_eq!(prefix.format(amount), "€12.00");
+        assert_eq!(suffix.format(amount), "12.00€");
+        assert_eq!(suffix.format(amount.neg()), "-12.00€");
+    }
+
+    #[test]
+    fn grouped_thousands() {
+        let money = MoneyFormat {
+            group_separator: Some(','),
+            ..MoneyFormat::default()
+        };
+
+        assert_eq!(money.format(Decimal::new(123_456_789, 2)), "£1,234,567.89");
+        assert_eq!(money.format(Decimal::new(-1_234_567, 2)), "£-12,345.67");
+        assert_eq!(money.format(Decimal::new(99_999, 2)), "£999.99");
+    }
+
+    #[test]
+    fn grouped_with_custom_separators() {
+        let money = MoneyFormat {
+            group_separator: Some('.'),
+            decimal_separator: ',',
+            ..MoneyFormat::default()
+        };
+
+        assert_eq!(money.format(Decimal::new(123_456_789, 2)), "£1.234.567,89");
+    }
+
+    #[test]
+    fn plain_drops_decorations() {
+        let money = MoneyFormat {
+            rounding: RoundingMode::Down,
+            symbol: "€".to_owned(),
+            position: Position::Suffix,
+            group_separator: Some('.'),
+            decimal_separator: ',',
+        };
+
+        assert_eq!(money.plain().format(Decimal::new(1_234_567, 3)), "1234.56");
+    }
+
+    #[test]
+    fn currency_codes_map_to_symbols() {
+        assert_eq!(currency_symbol("GBP"), "£");
+        assert_eq!(currency_symbol("usd"), "$");
+        assert_eq!(currency_symbol("XYZ"), "XYZ");
+    }
+
+    #[test]
+    fn pence_sums_are_exact() {
+        let pence: Pence = std::iter::repeat_n(Pence::from_pounds(Decimal::new(1, 1)), 30).sum();
+
+        assert_eq!(pence, Pence(300));
+        assert_eq!(Pence::from_pounds(Decimal::new(20, 0)), Pence(2000));
+        assert_eq!(Pence::from_pounds(Decimal::new(20_455, 3)), Pence(2046));
+        assert_eq!(Pence::from_pounds(Decimal::new(-20_455, 3)), Pence(-2046));
+        assert_eq!(Pence::from_pounds(Decimal::MAX), Pence(i64::MAX));
+        assert_eq!(Decimal::from(Pence(2000)).to_string(), "20.00");
+    }
+
+    #[test]
+    fn pence_shown_as_pounds() {
+        assert_eq!(Pence(1234).to_string(), "12.34");
+        assert_eq!((Pence(100) - Pence(105)).to_string(), "-0.05");
+        assert_eq!((-Pence(7)).to_string(), "-0.07");
+    }
+
+    #[test]
+    fn strip_configured_currency_symbol() {
+        assert_eq!(strip_currency_symbol("kr 20.00", Some("kr")), "20.00");
+        assert_eq!(strip_currency_symbol("kr20", None), "kr20");
+        assert_eq!(strip_currency_symbol("£20", Some("kr")), "20");
+        assert_eq!(strip_currency_symbol("20", Some("")), "20");
+    }
+}
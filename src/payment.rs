@@ -1,40 +1,362 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display, ops::RangeInclusive};
 
-use anyhow::Result;
-use chrono::{Datelike, NaiveDate};
-use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::utils;
+use crate::{
+    color::ColorThresholds,
+    error::BalanceError,
+    money::{
+        self, MoneyFormat, Pence, Position, RoundingMode, DEFAULT_CURRENCY_SYMBOL,
+        DEFAULT_DECIMAL_SEPARATOR, DEFAULT_GROUP_SEPARATOR,
+    },
+    utils,
+};
 
 const FILE_NAME: &str = "spend";
 const APP_NAME: &str = "balance";
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+pub const DAYS_PAID_RANGE: RangeInclusive<isize> = 1..=28;
+/// Days a config edited by hand can pay a bill on, with [`DayStrategy`] deciding what happens
+/// in months too short for them.
+pub const CONFIG_DAYS_RANGE: RangeInclusive<isize> = 1..=31;
+/// Most decimal places a payment amount can have.
+pub const MAX_AMOUNT_SCALE: u32 = 2;
+
+/// Rounds an amount to [`MAX_AMOUNT_SCALE`] decimal places, padding it with zeros if it has fewer.
+pub fn normalize_amount(amount: Decimal) -> Decimal {
+    let mut amount =
+        amount.round_dp_with_strategy(MAX_AMOUNT_SCALE, RoundingStrategy::MidpointAwayFromZero);
+    amount.rescale(MAX_AMOUNT_SCALE);
+    amount
+}
+
+/// Reads an amount written as a number or a string, padding it to [`MAX_AMOUNT_SCALE`] decimal
+/// places so hand-written values like `20` are stored as `20.00`.
+pub fn deserialize_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    let mut amount = <Decimal as Deserialize>::deserialize(deserializer)?;
+    if amount.scale() < MAX_AMOUNT_SCALE {
+        amount.rescale(MAX_AMOUNT_SCALE);
+    }
+    Ok(amount)
+}
+
+/// Checks an amount is non-negative and has at most [`MAX_AMOUNT_SCALE`] decimal places.
+pub fn validate_amount(amount: Decimal) -> Result<Decimal> {
+    if amount < Decimal::ZERO {
+        Err(BalanceError::Invalid("amount not greater than or equal to zero".to_owned()).into())
+    } else if amount.scale() > MAX_AMOUNT_SCALE {
+        Err(BalanceError::Invalid(format!(
+            "`{amount}` has more than {MAX_AMOUNT_SCALE} decimal places"
+        ))
+        .into())
+    } else {
+        Ok(amount)
+    }
+}
+
+/// Checks a day paid is within [`DAYS_PAID_RANGE`].
+pub fn validate_day_paid(day_paid: isize) -> Result<isize> {
+    if DAYS_PAID_RANGE.contains(&day_paid) {
+        Ok(day_paid)
+    } else {
+        Err(BalanceError::Invalid(format!(
+            "days paid not in range {}-{}",
+            DAYS_PAID_RANGE.start(),
+            DAYS_PAID_RANGE.end()
+        ))
+        .into())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Frequency {
+    /// Paid once a month on `day_paid`.
+    #[default]
+    Monthly,
+    /// Paid twice a month on the given days, `day_paid` is ignored.
+    SemiMonthly { first: isize, second: isize },
+}
+
+impl Frequency {
+    const fn is_monthly(&self) -> bool {
+        matches!(self, Self::Monthly)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Self::SemiMonthly { first, second } = self {
+            for day in [first, second] {
+                if !DAYS_PAID_RANGE.contains(day) {
+                    return Err(anyhow!(
+                        "semi-monthly day {day} not in range {}-{}",
+                        DAYS_PAID_RANGE.start(),
+                        DAYS_PAID_RANGE.end()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What happens to payments due after the last day of a short month.
+///
+/// `add`, `adjust` and `import` only take days up to the 28th, so this only applies to bills given
+/// a later day by editing the config by hand.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+pub enum DayStrategy {
+    /// Paid on the last day of the month instead.
+    #[default]
+    Clamp,
+    /// Not paid that month at all.
+    Skip,
+}
+
+impl DayStrategy {
+    /// The day a payment due on `day` is made in a month `days_in_month` long, or `None` if it
+    /// isn't paid that month.
+    pub fn day_in(self, day: isize, days_in_month: isize) -> Option<isize> {
+        match self {
+            Self::Clamp => Some(utils::clamp_day(day, days_in_month)),
+            Self::Skip => (day <= days_in_month).then(|| utils::clamp_day(day, days_in_month)),
+        }
+    }
+}
+
+/// What happens when a payment is due on a weekend.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum WeekendRule {
+    /// Taken on the weekend as normal.
+    #[default]
+    None,
+    /// Taken on the Friday before.
+    PrecedingFriday,
+    /// Taken on the Monday after.
+    FollowingMonday,
+}
+
+impl WeekendRule {
+    const fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// The date a payment due on `date` is actually taken.
+    pub fn apply(self, date: NaiveDate) -> NaiveDate {
+        let days = match (self, date.weekday()) {
+            (Self::PrecedingFriday, Weekday::Sat) => -1,
+            (Self::PrecedingFriday, Weekday::Sun) => -2,
+            (Self::FollowingMonday, Weekday::Sat) => 2,
+            (Self::FollowingMonday, Weekday::Sun) => 1,
+            _ => 0,
+        };
+        date + Duration::days(days)
+    }
+}
+
+/// A change to a payment's amount, kept so the cycle it takes effect in can be prorated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AmountChange {
+    /// Amount paid before the change.
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub previous: Decimal,
+    /// First day the new amount applies.
+    pub effective: NaiveDate,
+}
+
+/// Fields are stored in the order they are declared here, `name`, `amount` and `day_paid` first,
+/// so keep new fields at the end to avoid reordering every stored config.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct Payment {
     pub name: String,
+    #[serde(deserialize_with = "deserialize_amount")]
     pub amount: Decimal,
     pub day_paid: isize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Frequency::is_monthly")]
+    pub frequency: Frequency,
+    /// Archived payments are kept in the config but otherwise ignored.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "WeekendRule::is_none")]
+    pub weekend_rule: WeekendRule,
+    /// Free text notes about the bill. Comments in the config are lost when it is stored, so notes
+    /// that should be kept belong here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<AmountChange>,
+    /// Percentage of a shared bill that you pay, the full amount is paid when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_percent: Option<Decimal>,
+    /// First day the bill is paid, it is ignored before then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<NaiveDate>,
+    /// Last day the bill is paid, it is ignored after then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<NaiveDate>,
+    /// Day this bill's own cycle resets, such as a credit card's statement date, in place of the
+    /// reset day used for every other bill.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_day: Option<isize>,
+}
+
+/// Builds a [`Payment`], validating its fields on [`PaymentBuilder::build`].
+#[derive(Debug, Default)]
+pub struct PaymentBuilder {
+    name: Option<String>,
+    amount: Option<Decimal>,
+    day_paid: Option<isize>,
+    category: Option<String>,
+}
+
+impl PaymentBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub const fn amount(mut self, amount: Decimal) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub const fn day_paid(mut self, day_paid: isize) -> Self {
+        self.day_paid = Some(day_paid);
+        self
+    }
+
+    pub fn category(mut self, category: Option<impl Into<String>>) -> Self {
+        self.category = category.map(Into::into);
+        self
+    }
+
+    pub fn build(self) -> Result<Payment> {
+        let name = self.name.ok_or_else(|| anyhow!("a name is required"))?;
+        let amount = self
+            .amount
+            .ok_or_else(|| anyhow!("an amount is required"))?;
+        let day_paid = self
+            .day_paid
+            .ok_or_else(|| anyhow!("a day paid is required"))?;
+
+        Ok(Payment {
+            name,
+            amount: validate_amount(amount)?,
+            day_paid: validate_day_paid(day_paid)?,
+            category: self.category,
+            ..Payment::default()
+        })
+    }
 }
 
 impl Payment {
+    pub fn builder() -> PaymentBuilder {
+        PaymentBuilder::default()
+    }
+
     #[cfg(test)]
-    pub const fn new(name: String, amount: Decimal, day_paid: isize) -> Self {
+    pub fn new(name: String, amount: Decimal, day_paid: isize) -> Self {
         Self {
             name,
             amount,
             day_paid,
+            ..Self::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_owned());
+        self
+    }
+
+    /// Days of the month the payment is made on.
+    pub fn days_paid(&self) -> Vec<isize> {
+        match self.frequency {
+            Frequency::Monthly => vec![self.day_paid],
+            Frequency::SemiMonthly { first, second } => vec![first, second],
+        }
+    }
+
+    /// The days paid as text, such as `6`, or `1 and 15` for a semi-monthly payment.
+    pub fn days_paid_text(&self) -> String {
+        let days: Vec<String> = self.days_paid().iter().map(ToString::to_string).collect();
+        days.join(" and ")
+    }
+
+    /// The next date on or after `today` this payment is due.
+    pub fn next_due(&self, today: &NaiveDate) -> NaiveDate {
+        self.days_paid()
+            .into_iter()
+            .map(|day| self.weekend_rule.apply(utils::next_due(today, day)))
+            .min()
+            .expect("payments are paid at least once a month")
+    }
+
+    /// Your share of `amount` when the bill is shared, rounded to the penny.
+    pub fn share_of(&self, amount: Decimal) -> Decimal {
+        self.share_percent.map_or(amount, |percent| {
+            (amount * percent / Decimal::ONE_HUNDRED)
+                .round_dp_with_strategy(MAX_AMOUNT_SCALE, RoundingStrategy::MidpointAwayFromZero)
+        })
+    }
+
+    /// Your share of each payment.
+    pub fn share(&self) -> Decimal {
+        self.share_of(self.amount)
+    }
+
+    /// Whether the bill is paid on `reference_date`, so it isn't archived and the date is within
+    /// its start and end dates.
+    pub fn is_active(&self, reference_date: &NaiveDate) -> bool {
+        !self.archived
+            && self.start_date.is_none_or(|start| start <= *reference_date)
+            && self.end_date.is_none_or(|end| end >= *reference_date)
+    }
+
+    /// Your share of the total paid over a whole month.
+    pub fn monthly_cost(&self) -> Decimal {
+        self.share() * Decimal::from(self.days_paid().len())
+    }
+}
+
+pub struct PaymentDisplay<'a> {
+    payment: &'a Payment,
+    money: &'a MoneyFormat,
+}
+
+impl Payment {
+    pub const fn display<'a>(&'a self, money: &'a MoneyFormat) -> PaymentDisplay<'a> {
+        PaymentDisplay {
+            payment: self,
+            money,
         }
     }
 }
 
-impl Display for Payment {
+impl Display for PaymentDisplay<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Bill: {}\nAmount: £{}\nDay paid: {}",
-            self.name, self.amount, self.day_paid
-        )
+            "Bill: {}\nAmount: {}\nDay paid: {}",
+            self.payment.name,
+            self.money.format(self.payment.amount),
+            self.payment.day_paid
+        )?;
+        if let Some(description) = &self.payment.description {
+            write!(f, "\nDescription: {description}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Payment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.display(&MoneyFormat::default()).fmt(f)
     }
 }
 
@@ -60,15 +382,359 @@ impl Ord for Payment {
 
 pub type Payments = Vec<Payment>;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+pub trait PaymentsExt {
+    /// Maps each name to the position of its first payment, for checking many names at once.
+    fn index(&self) -> HashMap<&str, usize>;
+    /// Position of the first payment with the given name.
+    fn position_of(&self, name: &str) -> Option<usize>;
+    /// Position of the first payment with the given name, or an error suggesting a close name.
+    fn find(&self, name: &str) -> Result<usize>;
+    /// Total paid each month across every payment active on `reference_date`.
+    fn monthly_total(&self, reference_date: &NaiveDate) -> Decimal;
+    /// Total paid in the month of `month` across every payment active on it, leaving out days
+    /// `day_strategy` skips in that month.
+    fn total_in_month(&self, month: &NaiveDate, day_strategy: DayStrategy) -> Decimal;
+    /// The payments active on `reference_date`, see [`Payment::is_active`].
+    fn retain_active(&self, reference_date: &NaiveDate) -> Vec<&Payment>;
+}
+
+impl PaymentsExt for [Payment] {
+    fn index(&self) -> HashMap<&str, usize> {
+        let mut index = HashMap::with_capacity(self.len());
+        for (i, payment) in self.iter().enumerate() {
+            index.entry(payment.name.as_str()).or_insert(i);
+        }
+        index
+    }
+
+    fn position_of(&self, name: &str) -> Option<usize> {
+        self.iter().position(|p| p.name == name)
+    }
+
+    fn find(&self, name: &str) -> Result<usize> {
+        self.position_of(name).ok_or_else(|| {
+            let names = self.iter().map(|p| p.name.as_str());
+            let message = utils::closest_match(name, names).map_or_else(
+                || format!("{name} not found"),
+                |suggestion| format!("{name} not found, did you mean '{suggestion}'?"),
+            );
+            BalanceError::NotFound(message).into()
+        })
+    }
+
+    fn monthly_total(&self, reference_date: &NaiveDate) -> Decimal {
+        self.retain_active(reference_date)
+            .into_iter()
+            .map(Payment::monthly_cost)
+            .sum()
+    }
+
+    fn total_in_month(&self, month: &NaiveDate, day_strategy: DayStrategy) -> Decimal {
+        let days_in_month = utils::days_in_month(month);
+        self.retain_active(month)
+            .into_iter()
+            .map(|p| {
+                let times_paid = p
+                    .days_paid()
+                    .into_iter()
+                    .filter_map(|day| day_strategy.day_in(day, days_in_month))
+                    .count();
+                p.share() * Decimal::from(times_paid)
+            })
+            .sum()
+    }
+
+    fn retain_active(&self, reference_date: &NaiveDate) -> Vec<&Payment> {
+        self.iter()
+            .filter(|p| p.is_active(reference_date))
+            .collect()
+    }
+}
+
+const CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub version: u32,
     pub payments: Payments,
+    #[serde(default)]
+    pub rounding: RoundingMode,
+    #[serde(default = "default_currency_symbol")]
+    pub currency_symbol: String,
+    /// Three letter currency code, used in place of `currency_symbol` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_code: Option<String>,
+    #[serde(default)]
+    pub currency_position: Position,
+    #[serde(default)]
+    pub grouped: bool,
+    #[serde(default = "default_group_separator")]
+    pub group_separator: char,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Decimal>,
+    /// Monthly income, used to warn when the bills come to more.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub income: Option<Decimal>,
+    /// How payments due after the end of a short month are handled.
+    #[serde(default)]
+    pub round_day_strategy: DayStrategy,
+    /// Amount always kept aside, taken off the balance shown by `compute`.
+    #[serde(default, skip_serializing_if = "Decimal::is_zero")]
+    pub buffer: Decimal,
+    /// Days of the month that `add` and `adjust` won't let bills be paid on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blackout_days: Vec<isize>,
+    /// Balances `compute` shows in green, amber or red.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_thresholds: Option<ColorThresholds>,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+}
+
+/// Keys in `value` that aren't in `known`, a stored config with every field set, named by where
+/// they are such as `payments[0].categroy`.
+fn unknown_keys(value: &serde_yaml::Value, known: &serde_yaml::Value, path: &str) -> Vec<String> {
+    use serde_yaml::Value;
+    match (value, known) {
+        (Value::Mapping(fields), Value::Mapping(known_fields)) => fields
+            .iter()
+            .flat_map(|(key, field)| {
+                let name = key
+                    .as_str()
+                    .map_or_else(|| format!("{key:?}"), str::to_owned);
+                let path = if path.is_empty() {
+                    name
+                } else {
+                    format!("{path}.{name}")
+                };
+                known_fields.get(key).map_or_else(
+                    || vec![path.clone()],
+                    |known_field| unknown_keys(field, known_field, &path),
+                )
+            })
+            .collect(),
+        (Value::Sequence(items), Value::Sequence(known_items)) => {
+            known_items.first().map_or_else(Vec::new, |known_item| {
+                items
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, item)| unknown_keys(item, known_item, &format!("{path}[{i}]")))
+                    .collect()
+            })
+        }
+        _ => vec![],
+    }
+}
+
+/// Errors if the config text contains any keys [`Config`] doesn't know about.
+///
+/// The known keys are taken from [`example_config`] as it is stored, so they always match what
+/// [`Config`] writes.
+pub fn check_strict(contents: &str) -> Result<()> {
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(contents).map_err(|e| anyhow!("strict config check failed: {e}"))?;
+    let known = serde_yaml::to_value(example_config())?;
+    let unknown = unknown_keys(&value, &known, "");
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let keys: Vec<String> = unknown.iter().map(|k| format!("`{k}`")).collect();
+    let plural = if keys.len() == 1 { "" } else { "s" };
+    Err(anyhow!(
+        "strict config check failed: unknown field{plural} {}",
+        keys.join(", ")
+    ))
+}
+
+fn default_date_format() -> String {
+    utils::DEFAULT_DATE_FORMAT.to_owned()
+}
+
+fn default_currency_symbol() -> String {
+    DEFAULT_CURRENCY_SYMBOL.to_owned()
+}
+
+const fn default_group_separator() -> char {
+    DEFAULT_GROUP_SEPARATOR
+}
+
+const fn default_decimal_separator() -> char {
+    DEFAULT_DECIMAL_SEPARATOR
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            payments: Payments::default(),
+            rounding: RoundingMode::default(),
+            currency_symbol: default_currency_symbol(),
+            currency_code: None,
+            currency_position: Position::default(),
+            grouped: false,
+            group_separator: default_group_separator(),
+            decimal_separator: default_decimal_separator(),
+            budget: None,
+            income: None,
+            buffer: Decimal::ZERO,
+            blackout_days: vec![],
+            color_thresholds: None,
+            round_day_strategy: DayStrategy::default(),
+            date_format: default_date_format(),
+        }
+    }
+}
+
+/// A config with every field set, and none left to a default that is skipped when stored, so its
+/// stored form shows every key. Fields are listed rather than defaulted so a new one can't be
+/// missed.
+pub fn example_config() -> Config {
+    let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+    Config {
+        version: CONFIG_VERSION,
+        payments: vec![Payment {
+            name: "Rent".to_owned(),
+            amount: Decimal::new(60000, 2),
+            day_paid: 1,
+            category: Some("Home".to_owned()),
+            frequency: Frequency::SemiMonthly {
+                first: 1,
+                second: 15,
+            },
+            archived: true,
+            weekend_rule: WeekendRule::PrecedingFriday,
+            description: Some("Split with a flatmate".to_owned()),
+            change: Some(AmountChange {
+                previous: Decimal::new(55000, 2),
+                effective: date(1, 1),
+            }),
+            share_percent: Some(Decimal::new(50, 0)),
+            start_date: Some(date(1, 1)),
+            end_date: Some(date(12, 31)),
+            reset_day: Some(10),
+        }],
+        rounding: RoundingMode::HalfEven,
+        currency_symbol: "€".to_owned(),
+        currency_code: Some("EUR".to_owned()),
+        currency_position: Position::Suffix,
+        grouped: true,
+        group_separator: '.',
+        decimal_separator: ',',
+        budget: Some(Decimal::new(100_000, 2)),
+        income: Some(Decimal::new(200_000, 2)),
+        round_day_strategy: DayStrategy::Skip,
+        buffer: Decimal::new(5000, 2),
+        blackout_days: vec![1],
+        color_thresholds: Some(ColorThresholds {
+            low: Decimal::new(5000, 2),
+            high: Decimal::new(20000, 2),
+        }),
+        date_format: "%d.%m.%Y".to_owned(),
+    }
 }
 
-pub fn get_config() -> Result<Config> {
+impl Config {
+    pub fn money_format(&self) -> MoneyFormat {
+        MoneyFormat {
+            rounding: self.rounding,
+            symbol: self
+                .currency_code
+                .as_deref()
+                .map_or_else(|| self.currency_symbol.clone(), money::currency_symbol),
+            position: self.currency_position,
+            group_separator: self.grouped.then_some(self.group_separator),
+            decimal_separator: self.decimal_separator,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        utils::validate_date_format(&self.date_format)?;
+        for payment in &self.payments {
+            payment
+                .frequency
+                .validate()
+                .map_err(|e| anyhow!("{}: {e}", payment.name))?;
+            if let Some(percent) = payment.share_percent {
+                if percent <= Decimal::ZERO || percent > Decimal::ONE_HUNDRED {
+                    return Err(anyhow!(
+                        "{}: share percent {percent} not in range 0-100",
+                        payment.name
+                    ));
+                }
+            }
+            if let Some(day) = payment.reset_day {
+                if !DAYS_PAID_RANGE.contains(&day) {
+                    return Err(anyhow!(
+                        "{}: reset day {day} not in range {}-{}",
+                        payment.name,
+                        DAYS_PAID_RANGE.start(),
+                        DAYS_PAID_RANGE.end()
+                    ));
+                }
+            }
+        }
+        if let Some(ColorThresholds { low, high }) = self.color_thresholds {
+            if low > high {
+                return Err(anyhow!("color threshold low {low} is above high {high}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether both configs would be stored the same way.
+    ///
+    /// Payments compare equal by name alone, so the serialised forms are compared to include
+    /// every field.
+    pub fn same_contents(&self, other: &Self) -> bool {
+        match (serde_yaml::to_value(self), serde_yaml::to_value(other)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Errors if bills can't be paid on `day_paid` because it is one of the blackout days.
+    pub fn check_blackout(&self, day_paid: isize) -> Result<()> {
+        if self.blackout_days.contains(&day_paid) {
+            Err(BalanceError::Invalid(format!(
+                "day {day_paid} is a blackout day, choose another day to pay the bill on"
+            ))
+            .into())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub const fn migrate(mut self) -> Self {
+        if self.version < 1 {
+            // Version 1 introduced the optional category, which serde defaults to `None`.
+            self.version = 1;
+        }
+        self
+    }
+}
+
+pub fn get_config(strict: bool) -> Result<Config> {
+    if strict {
+        let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME))?;
+        if path.exists() {
+            check_strict(&std::fs::read_to_string(path)?)?;
+        }
+    }
     let config: Config = confy::load(APP_NAME, Some(FILE_NAME))?;
+    config.validate()?;
+
+    Ok(config.migrate())
+}
+
+/// Loads a config from a file other than the user's own, such as one from another machine.
+pub fn read_config(path: &std::path::Path) -> Result<Config> {
+    let config: Config = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    config.validate()?;
 
-    Ok(config)
+    Ok(config.migrate())
 }
 
 pub fn store_config(config: &Config) -> Result<()> {
@@ -76,6 +742,46 @@ pub fn store_config(config: &Config) -> Result<()> {
     Ok(())
 }
 
+pub fn backup_config() -> Result<()> {
+    let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME))?;
+    if path.exists() {
+        std::fs::copy(&path, path.with_extension("yml.bak"))?;
+    }
+    Ok(())
+}
+
+/// File locked while a command runs, kept next to the config.
+pub fn config_lock_path() -> Result<std::path::PathBuf> {
+    let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(path.with_extension("yml.lock"))
+}
+
+/// The currency symbol shown by the stored config, or `None` if there isn't a config yet or it
+/// can't be read. Unlike [`get_config`], this never creates the config.
+pub fn stored_currency_symbol() -> Option<String> {
+    let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME)).ok()?;
+    let config: Config = serde_yaml::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    Some(config.money_format().symbol)
+}
+
+/// When the config file was last changed, or `None` if there isn't one yet.
+pub fn config_modified() -> Result<Option<std::time::SystemTime>> {
+    let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME))?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::metadata(path)?.modified()?))
+}
+
+/// File the last `compute` result is cached in, kept next to the config.
+pub fn compute_cache_path() -> Result<std::path::PathBuf> {
+    let path = confy::get_configuration_file_path(APP_NAME, Some(FILE_NAME))?;
+    Ok(path.with_extension("yml.cache"))
+}
+
 pub fn edit_config() -> Result<()> {
     edit::edit_file(confy::get_configuration_file_path(
         APP_NAME,
@@ -89,34 +795,309 @@ pub struct PaymentManager {
     balance: Decimal,
     reset_day: isize,
     payments: Payments,
+    income: Option<Decimal>,
+    day_strategy: DayStrategy,
+    prorate: bool,
+    /// Whether `balance` was taken at the start of the cycle, before any of its bills were paid.
+    balance_at_cycle_start: bool,
 }
 
 impl PaymentManager {
-    pub const fn new(balance: Decimal, reset_day: isize, payments: Payments) -> Self {
+    /// Every command that works out what is paid when goes through here, so the configured
+    /// `day_strategy` is applied everywhere.
+    pub const fn new(
+        balance: Decimal,
+        reset_day: isize,
+        payments: Payments,
+        day_strategy: DayStrategy,
+    ) -> Self {
         Self {
             balance,
             reset_day,
             payments,
+            income: None,
+            day_strategy,
+            prorate: false,
+            balance_at_cycle_start: false,
         }
     }
 
-    pub fn remaining_balance(&self, current_day: &NaiveDate) -> Decimal {
+    /// Treats the balance as taken when the cycle reset, so forecasts take every bill in the
+    /// current cycle off it rather than only those still to come.
+    pub const fn with_balance_at_cycle_start(mut self, at_start: bool) -> Self {
+        self.balance_at_cycle_start = at_start;
+        self
+    }
+
+    /// Weights payments whose amount changed during the cycle by the days at each amount.
+    pub const fn with_prorate(mut self, prorate: bool) -> Self {
+        self.prorate = prorate;
+        self
+    }
+
+    pub const fn with_income(mut self, income: Decimal) -> Self {
+        self.income = Some(income);
+        self
+    }
+
+    /// Keeps `buffer` aside, so it is never counted as left to spend.
+    pub fn with_buffer(mut self, buffer: Decimal) -> Self {
+        self.balance -= buffer;
+        self
+    }
+
+    pub fn report(&self, current_day: &NaiveDate) -> BalanceReport<'_> {
         let rd = self.reset_day;
         let day = current_day.day() as isize;
         let days_in_month = utils::days_in_month(current_day);
 
         let rebased_cd = utils::modulo(day - rd, days_in_month);
+        let (cycle_start, _) = self.cycle_bounds(current_day);
 
-        let leftover_payments: Decimal = self
+        let details: Vec<PaymentDetail> = self
             .payments
+            .retain_active(current_day)
+            .into_iter()
+            .flat_map(|payment| {
+                payment
+                    .days_paid()
+                    .into_iter()
+                    .filter_map(|day_paid| self.day_strategy.day_in(day_paid, days_in_month))
+                    .map(move |day_paid| {
+                        let own_rd = payment.reset_day.unwrap_or(rd);
+                        let due = utils::cycle_date(current_day, own_rd, day_paid);
+                        let shift = (payment.weekend_rule.apply(due) - due).num_days() as isize;
+                        let rebased_day = utils::modulo(day_paid + shift - own_rd, days_in_month);
+                        let today = utils::modulo(day - own_rd, days_in_month);
+                        PaymentDetail {
+                            payment,
+                            amount: self.cycle_amount(payment, &cycle_start, days_in_month),
+                            rebased_day,
+                            today,
+                            leftover: rebased_day > today,
+                        }
+                    })
+            })
+            .collect();
+
+        let leftover_payments: Pence = details
+            .iter()
+            .filter(|d| d.leftover)
+            .map(|d| d.amount)
+            .sum();
+
+        BalanceReport {
+            rebased_day: rebased_cd,
+            cycle_length: days_in_month,
+            next_reset: utils::next_reset(current_day, rd),
+            remaining: self.balance - Decimal::from(leftover_payments),
+            details,
+        }
+    }
+
+    /// First day and length of the bill cycle containing `current_day`.
+    fn cycle_bounds(&self, current_day: &NaiveDate) -> (NaiveDate, isize) {
+        let days_in_month = utils::days_in_month(current_day);
+        let rebased_day = utils::modulo(current_day.day() as isize - self.reset_day, days_in_month);
+        (
+            *current_day - Duration::days(rebased_day as i64),
+            days_in_month,
+        )
+    }
+
+    /// Amount of `payment` counted in the cycle starting on `cycle_start`, to the nearest penny.
+    fn cycle_amount(
+        &self,
+        payment: &Payment,
+        cycle_start: &NaiveDate,
+        cycle_length: isize,
+    ) -> Pence {
+        let amount = match payment.change {
+            Some(change) if self.prorate => {
+                let days_before = (change.effective - *cycle_start)
+                    .num_days()
+                    .clamp(0, cycle_length as i64);
+                let length = Decimal::from(cycle_length);
+                let before = Decimal::from(days_before);
+                payment.share_of(
+                    ((change.previous * before + payment.amount * (length - before)) / length)
+                        .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero),
+                )
+            }
+            _ => payment.share(),
+        };
+        Pence::from_pounds(amount)
+    }
+
+    /// Payments still to be taken this cycle, once for each time they are due.
+    pub fn cycle_payments(&self, current_day: &NaiveDate) -> Vec<&Payment> {
+        self.report(current_day)
+            .details
+            .into_iter()
+            .filter(|d| d.leftover)
+            .map(|d| d.payment)
+            .collect()
+    }
+
+    /// Balance left once this cycle's remaining payments are taken, with the payments summed in
+    /// whole pence.
+    pub fn remaining_balance(&self, current_day: &NaiveDate) -> Decimal {
+        let (cycle_start, cycle_length) = self.cycle_bounds(current_day);
+        let leftover: Pence = self
+            .cycle_payments(current_day)
+            .iter()
+            .map(|p| self.cycle_amount(p, &cycle_start, cycle_length))
+            .sum();
+        self.balance - Decimal::from(leftover)
+    }
+
+    /// The balance with the bills still to come this cycle added back, for a balance they have
+    /// already been taken from, such as one showing pending bills.
+    pub fn balance_before_bills(&self, current_day: &NaiveDate) -> Decimal {
+        let leftover = self.balance - self.remaining_balance(current_day);
+        self.balance + leftover
+    }
+
+    /// Balance left at the end of the current cycle, before any income.
+    fn cycle_end_balance(&self, current_day: &NaiveDate) -> Decimal {
+        if !self.balance_at_cycle_start {
+            return self.remaining_balance(current_day);
+        }
+        let bills: Pence = self
+            .report(current_day)
+            .details
             .iter()
-            .map(|p| (p.amount, utils::modulo(p.day_paid - rd, days_in_month)))
-            .filter(|p| p.1 > rebased_cd)
-            .map(|p| p.0)
+            .map(|d| d.amount)
             .sum();
+        self.balance - Decimal::from(bills)
+    }
+
+    /// Remaining balance spread evenly over the days left in the cycle, including today.
+    pub fn safe_daily_spend(&self, current_day: &NaiveDate) -> Decimal {
+        let report = self.report(current_day);
+        let days_left = report.cycle_length - report.rebased_day;
+
+        (report.remaining / Decimal::from(days_left)).max(Decimal::ZERO)
+    }
 
-        self.balance - leftover_payments
+    /// Whole days the remaining balance lasts at the cycle's average daily bill spend, or `None` if
+    /// there are no bills to spend it on.
+    pub fn runway_days(&self, current_day: &NaiveDate) -> Option<Decimal> {
+        let report = self.report(current_day);
+        let daily_spend =
+            self.payments.monthly_total(current_day) / Decimal::from(report.cycle_length);
+        if daily_spend.is_zero() {
+            return None;
+        }
+
+        Some((report.remaining / daily_spend).floor().max(Decimal::ZERO))
     }
+
+    /// First date after `current_day`, within the next `months`, that the balance would go
+    /// negative if no income is paid in, or `None` if it lasts the whole window.
+    pub fn zero_date(&self, current_day: &NaiveDate, months: u32) -> Option<NaiveDate> {
+        let end = *current_day + Months::new(months);
+        let start_of_month = current_day.with_day(1).unwrap();
+
+        let mut due: Vec<(NaiveDate, Decimal)> = (0..=months)
+            .map(|i| start_of_month + Months::new(i))
+            .flat_map(|month| {
+                let days_in_month = utils::days_in_month(&month);
+                self.payments
+                    .iter()
+                    .flat_map(|p| p.days_paid().into_iter().map(move |day| (p, day)))
+                    .filter_map(move |(p, day)| {
+                        let day = self.day_strategy.day_in(day, days_in_month)?;
+                        Some((p, month.with_day(day as u32).unwrap()))
+                    })
+                    .filter(|(p, date)| p.is_active(date))
+                    .map(|(p, date)| (p.weekend_rule.apply(date), p.share()))
+            })
+            .filter(|(date, _)| date > current_day && *date <= end)
+            .collect();
+        due.sort_by_key(|(date, _)| *date);
+
+        let mut balance = self.balance;
+        due.into_iter().find_map(|(date, amount)| {
+            balance -= amount;
+            (balance < Decimal::ZERO).then_some(date)
+        })
+    }
+
+    /// Balance left at the end of this cycle and each of the following `cycles`, with any income
+    /// paid in as each cycle resets.
+    pub fn projection(&self, current_day: &NaiveDate, cycles: usize) -> Vec<Decimal> {
+        let income = self.income.unwrap_or_default();
+
+        let mut balance = self.cycle_end_balance(current_day);
+        let mut start = *current_day;
+        let mut balances = vec![balance];
+        for _ in 0..cycles {
+            start = utils::next_reset(&start, self.reset_day);
+            balance += income - self.payments.total_in_month(&start, self.day_strategy);
+            balances.push(balance);
+        }
+        balances
+    }
+
+    /// One row for each of the next `months` bill cycles after the current one, with the balance
+    /// left at the end of this cycle carried into the first.
+    pub fn monthly_forecast(&self, current_day: &NaiveDate, months: usize) -> Vec<MonthForecast> {
+        let income = self.income.unwrap_or_default();
+
+        let mut balance = self.cycle_end_balance(current_day);
+        let mut start = *current_day;
+        let mut rows = vec![];
+        for _ in 0..months {
+            start = utils::next_reset(&start, self.reset_day);
+            let bills = self.payments.total_in_month(&start, self.day_strategy);
+            balance += income - bills;
+            rows.push(MonthForecast {
+                start,
+                income,
+                bills,
+                balance,
+            });
+        }
+        rows
+    }
+}
+
+/// Money in and out over one forecast bill cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthForecast {
+    /// Day the cycle starts on.
+    pub start: NaiveDate,
+    pub income: Decimal,
+    pub bills: Decimal,
+    /// Balance left at the end of the cycle.
+    pub balance: Decimal,
+}
+
+/// A single occurrence of a payment within the bill cycle.
+#[derive(Debug, Clone)]
+pub struct PaymentDetail<'a> {
+    pub payment: &'a Payment,
+    /// Amount taken off the balance for this payment, your share of it and prorated if asked.
+    pub amount: Pence,
+    /// Day of the payment's bill cycle it is made on, counting the reset day as 0. This is its
+    /// own cycle when the payment has its own reset day.
+    pub rebased_day: isize,
+    /// Day of the payment's bill cycle the balance is computed on, counted the same way.
+    pub today: isize,
+    /// Whether the payment is still to come out of the balance this cycle.
+    pub leftover: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceReport<'a> {
+    /// Day of the bill cycle the balance is computed on, counting the reset day as 0.
+    pub rebased_day: isize,
+    /// Number of days in the bill cycle, taken from the current month.
+    pub cycle_length: isize,
+    pub next_reset: NaiveDate,
+    pub remaining: Decimal,
+    pub details: Vec<PaymentDetail<'a>>,
 }
 
 #[cfg(test)]
@@ -126,6 +1107,11 @@ mod tests {
     use rust_decimal::Decimal;
 
     use super::*;
+    use crate::utils::assert_money_eq;
+
+    fn manager(balance: Decimal, reset_day: isize, payments: Payments) -> PaymentManager {
+        PaymentManager::new(balance, reset_day, payments, DayStrategy::Clamp)
+    }
 
     #[test]
     fn start_of_period() {
@@ -134,11 +1120,11 @@ mod tests {
             Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
         ];
 
-        let payment_manager = PaymentManager::new(Decimal::new(10000, 2), 18, payments);
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
 
         let remaining =
             payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-19").unwrap());
-        assert_eq!(remaining, Decimal::new(7000, 2));
+        assert_money_eq(remaining, "70.00");
     }
 
     #[test]
@@ -148,11 +1134,11 @@ mod tests {
             Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
         ];
 
-        let payment_manager = PaymentManager::new(Decimal::new(10000, 2), 18, payments);
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
 
         let remaining =
             payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-01").unwrap());
-        assert_eq!(remaining, Decimal::new(8000, 2));
+        assert_money_eq(remaining, "80.00");
     }
 
     #[test]
@@ -162,11 +1148,11 @@ mod tests {
             Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
         ];
 
-        let payment_manager = PaymentManager::new(Decimal::new(10000, 2), 18, payments);
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
 
         let remaining =
             payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-28").unwrap());
-        assert_eq!(remaining, Decimal::new(8000, 2));
+        assert_money_eq(remaining, "80.00");
     }
 
     #[test]
@@ -175,12 +1161,12 @@ mod tests {
             Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
             Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
         ];
-        let payment_manager = PaymentManager::new(Decimal::new(10000, 2), 18, payments);
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
 
         let remaining =
             payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-31").unwrap());
 
-        assert_eq!(remaining, Decimal::new(8000, 2));
+        assert_money_eq(remaining, "80.00");
     }
 
     #[test]
@@ -189,12 +1175,12 @@ mod tests {
             Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
             Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
         ];
-        let payment_manager = PaymentManager::new(Decimal::new(10000, 2), 18, payments);
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
 
         let remaining =
             payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-18").unwrap());
 
-        assert_eq!(remaining, Decimal::new(7000, 2));
+        assert_money_eq(remaining, "70.00");
     }
 
     #[test]
@@ -213,4 +1199,740 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn migrate_unversioned_config() {
+        let config = Config {
+            version: 0,
+            payments: vec![Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3)],
+            ..Config::default()
+        };
+
+        let config = config.migrate();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.payments[0].category, None);
+    }
+
+    #[test]
+    fn report_marks_leftover_payments() {
+        let payments = vec![
+            Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
+
+        let report = payment_manager.report(&NaiveDate::from_str("2023-01-31").unwrap());
+
+        assert_eq!(report.rebased_day, 13);
+        let details: Vec<(isize, bool)> = report
+            .details
+            .iter()
+            .map(|d| (d.rebased_day, d.leftover))
+            .collect();
+        assert_eq!(details, vec![(10, false), (16, true)]);
+        assert_money_eq(report.remaining, "80.00");
+    }
+
+    #[test]
+    fn projection_applies_income_at_reset() {
+        let payments = vec![
+            Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        let payment_manager =
+            manager(Decimal::new(10000, 2), 18, payments).with_income(Decimal::new(5000, 2));
+
+        let projection = payment_manager.projection(&NaiveDate::from_str("2023-01-19").unwrap(), 2);
+
+        assert_money_eq(projection[0], "70.00");
+        assert_money_eq(projection[1], "90.00");
+        assert_money_eq(projection[2], "110.00");
+    }
+
+    #[test]
+    fn projection_without_income() {
+        let payments = vec![Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3)];
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
+
+        let projection = payment_manager.projection(&NaiveDate::from_str("2023-01-19").unwrap(), 1);
+
+        assert_money_eq(projection[0], "80.00");
+        assert_money_eq(projection[1], "60.00");
+    }
+
+    #[test]
+    fn display_uses_currency_position() {
+        let payment = Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3);
+        let config = Config {
+            currency_symbol: "€".to_owned(),
+            currency_position: Position::Suffix,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            payment.to_string(),
+            "Bill: Water\nAmount: £20.00\nDay paid: 3"
+        );
+        assert_eq!(
+            payment.display(&config.money_format()).to_string(),
+            "Bill: Water\nAmount: 20.00€\nDay paid: 3"
+        );
+    }
+
+    fn semi_monthly_manager() -> PaymentManager {
+        let mut rent = Payment::new("Rent".to_owned(), Decimal::new(30000, 2), 0);
+        rent.frequency = Frequency::SemiMonthly {
+            first: 1,
+            second: 15,
+        };
+        let payments = vec![
+            rent,
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        manager(Decimal::new(100_000, 2), 28, payments)
+    }
+
+    #[test]
+    fn semi_monthly_both_upcoming() {
+        let remaining =
+            semi_monthly_manager().remaining_balance(&NaiveDate::from_str("2023-01-28").unwrap());
+
+        assert_money_eq(remaining, "380.00");
+    }
+
+    #[test]
+    fn semi_monthly_one_passed() {
+        let remaining =
+            semi_monthly_manager().remaining_balance(&NaiveDate::from_str("2023-02-10").unwrap());
+
+        assert_money_eq(remaining, "700.00");
+    }
+
+    #[test]
+    fn semi_monthly_days_validated() {
+        let frequency = Frequency::SemiMonthly {
+            first: 1,
+            second: 30,
+        };
+
+        assert!(frequency.validate().is_err());
+    }
+
+    #[test]
+    fn days_clamped_to_month_length() {
+        let payments = vec![Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 31)];
+        let payment_manager = manager(Decimal::new(10000, 2), 1, payments);
+
+        let remaining =
+            payment_manager.remaining_balance(&NaiveDate::from_str("2023-02-27").unwrap());
+
+        assert_money_eq(remaining, "70.00");
+    }
+
+    #[test]
+    fn safe_daily_spend_over_rest_of_cycle() {
+        let payments = vec![Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3)];
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
+
+        let daily = payment_manager.safe_daily_spend(&NaiveDate::from_str("2023-01-21").unwrap());
+
+        assert_eq!(daily, Decimal::new(80, 0) / Decimal::new(28, 0));
+    }
+
+    #[test]
+    fn safe_daily_spend_never_negative() {
+        let payments = vec![Payment::new("Water".to_owned(), Decimal::new(20000, 2), 3)];
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
+
+        let daily = payment_manager.safe_daily_spend(&NaiveDate::from_str("2023-01-21").unwrap());
+
+        assert_eq!(daily, Decimal::ZERO);
+    }
+
+    #[test]
+    fn index_lookup_matches_scan() {
+        let mut payments: Payments = (0..10_000)
+            .map(|i| Payment::new(format!("Bill {i}"), Decimal::new(i, 2), i as isize % 28 + 1))
+            .collect();
+        payments.push(Payment::new("Bill 42".to_owned(), Decimal::ZERO, 1));
+
+        let index = payments.index();
+
+        assert_eq!(index.len(), 10_000);
+        for (i, payment) in payments.iter().enumerate().take(10_000) {
+            assert_eq!(index[payment.name.as_str()], i);
+        }
+        assert_eq!(payments.position_of("Bill 9999"), Some(9999));
+        assert_eq!(payments.position_of("Bill 42"), Some(42));
+        assert_eq!(payments.position_of("Missing"), None);
+    }
+
+    #[test]
+    fn archived_payments_do_not_affect_balance() {
+        let mut phone = Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28);
+        phone.archived = true;
+        let payments = vec![
+            phone,
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        let payment_manager =
+            manager(Decimal::new(10000, 2), 18, payments).with_income(Decimal::new(2000, 2));
+
+        let current_day = NaiveDate::from_str("2023-01-19").unwrap();
+
+        assert_money_eq(payment_manager.remaining_balance(&current_day), "80.00");
+        let projection = payment_manager.projection(&current_day, 1);
+        assert_money_eq(projection[0], "80.00");
+        assert_money_eq(projection[1], "80.00");
+    }
+
+    #[test]
+    fn builder_valid_payment() {
+        let payment = Payment::builder()
+            .name("Gas")
+            .amount(Decimal::new(2000, 2))
+            .day_paid(6)
+            .category(Some("Energy"))
+            .build()
+            .unwrap();
+
+        assert_eq!(payment.name, "Gas");
+        assert_money_eq(payment.amount, "20.00");
+        assert_eq!(payment.day_paid, 6);
+        assert_eq!(payment.category.as_deref(), Some("Energy"));
+    }
+
+    fn build_error(builder: PaymentBuilder) -> String {
+        builder.build().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn builder_invalid_payments() {
+        let valid = || {
+            Payment::builder()
+                .name("Gas")
+                .amount(Decimal::new(2000, 2))
+                .day_paid(6)
+        };
+
+        assert_eq!(
+            build_error(valid().amount(Decimal::new(-1, 0))),
+            "amount not greater than or equal to zero"
+        );
+        assert_eq!(
+            build_error(valid().amount(Decimal::new(20001, 3))),
+            "`20.001` has more than 2 decimal places"
+        );
+        assert_eq!(
+            build_error(valid().day_paid(0)),
+            "days paid not in range 1-28"
+        );
+        assert_eq!(
+            build_error(valid().day_paid(29)),
+            "days paid not in range 1-28"
+        );
+        assert_eq!(
+            build_error(Payment::builder().amount(Decimal::ONE).day_paid(1)),
+            "a name is required"
+        );
+    }
+
+    #[test]
+    fn unknown_config_key_only_errors_when_strict() {
+        let contents = "payments:
+- name: Gas
+  amount: 20.00
+  day_paid: 6
+  categroy: Energy
+";
+
+        let error = check_strict(contents).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "strict config check failed: unknown field `payments[0].categroy`"
+        );
+
+        let config: Config = serde_yaml::from_str(contents).unwrap();
+        assert_eq!(config.payments[0].category, None);
+    }
+
+    #[test]
+    fn default_config_passes_strict_check() {
+        let mut config = Config {
+            budget: Some(Decimal::ONE_HUNDRED),
+            ..Config::default()
+        };
+        let mut payment = Payment::new("Gas".to_owned(), Decimal::ONE, 6).with_category("Energy");
+        payment.archived = true;
+        config.payments.push(payment);
+
+        check_strict(&serde_yaml::to_string(&config).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn strict_check_reports_nested_unknown_keys() {
+        let contents = "payments:
+- name: Gas
+  amount: 20.00
+  day_paid: 6
+  frequency:
+    type: Monthly
+- name: Rent
+  amount: 500.00
+  day_paid: 1
+  change:
+    previous: 450.00
+    effective: 2023-01-01
+    reason: rise
+budgit: 100
+";
+
+        check_strict(&serde_yaml::to_string(&example_config()).unwrap()).unwrap();
+        assert_eq!(
+            check_strict(contents).unwrap_err().to_string(),
+            "strict config check failed: unknown fields `payments[1].change.reason`, `budgit`"
+        );
+    }
+
+    #[test]
+    fn normalize_amount_to_two_places() {
+        assert_eq!(normalize_amount(Decimal::new(125, 1)).to_string(), "12.50");
+        assert_eq!(normalize_amount(Decimal::new(12, 0)).to_string(), "12.00");
+        assert_eq!(
+            normalize_amount(Decimal::new(12345, 3)).to_string(),
+            "12.35"
+        );
+    }
+
+    #[test]
+    fn saturday_bill_taken_on_friday_is_already_paid() {
+        // 2023-01-07 is a Saturday.
+        let today = NaiveDate::from_ymd_opt(2023, 1, 6).unwrap();
+        let bill = |weekend_rule| Payment {
+            weekend_rule,
+            ..Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 7)
+        };
+        let remaining = |weekend_rule| {
+            manager(Decimal::ONE_HUNDRED, 18, vec![bill(weekend_rule)]).remaining_balance(&today)
+        };
+
+        assert_money_eq(remaining(WeekendRule::None), "70.00");
+        assert_money_eq(remaining(WeekendRule::PrecedingFriday), "100.00");
+        assert_money_eq(remaining(WeekendRule::FollowingMonday), "70.00");
+    }
+
+    #[test]
+    fn weekend_rule_shifts_next_due() {
+        let today = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let payment = Payment {
+            weekend_rule: WeekendRule::FollowingMonday,
+            ..Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 7)
+        };
+
+        assert_eq!(
+            payment.next_due(&today),
+            NaiveDate::from_ymd_opt(2023, 1, 9).unwrap()
+        );
+    }
+
+    #[test]
+    fn currency_code_overrides_symbol() {
+        let format = |code: &str| {
+            Config {
+                currency_symbol: "$".to_owned(),
+                currency_code: Some(code.to_owned()),
+                ..Config::default()
+            }
+            .money_format()
+            .format(Decimal::new(1000, 2))
+        };
+
+        assert_eq!(format("GBP"), "£10.00");
+        assert_eq!(format("XYZ"), "XYZ10.00");
+    }
+
+    fn cycle_names(payment_manager: &PaymentManager, date: &str) -> Vec<String> {
+        payment_manager
+            .cycle_payments(&NaiveDate::from_str(date).unwrap())
+            .into_iter()
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn cycle_payments_are_the_leftover_set() {
+        let payments = vec![
+            Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        let payment_manager = manager(Decimal::new(10000, 2), 18, payments);
+
+        assert_eq!(
+            cycle_names(&payment_manager, "2023-01-19"),
+            vec!["Phone", "Water"]
+        );
+        assert_eq!(cycle_names(&payment_manager, "2023-01-01"), vec!["Water"]);
+        assert_eq!(cycle_names(&payment_manager, "2023-01-28"), vec!["Water"]);
+        assert_eq!(
+            cycle_names(&payment_manager, "2023-02-10"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn cycle_payments_repeat_semi_monthly_bills() {
+        assert_eq!(
+            cycle_names(&semi_monthly_manager(), "2023-01-28"),
+            vec!["Rent", "Rent", "Water"]
+        );
+    }
+
+    #[test]
+    fn day_31_bill_in_february() {
+        let today = NaiveDate::from_str("2023-02-10").unwrap();
+        let remaining = |strategy| {
+            let payments = vec![Payment::new("Rent".to_owned(), Decimal::new(5000, 2), 31)];
+            PaymentManager::new(Decimal::ONE_HUNDRED, 1, payments, strategy)
+                .remaining_balance(&today)
+        };
+
+        assert_money_eq(remaining(DayStrategy::Clamp), "50.00");
+        assert_money_eq(remaining(DayStrategy::Skip), "100.00");
+    }
+
+    #[test]
+    fn amounts_read_from_numbers_and_strings() {
+        let amount = |yaml: &str| {
+            let yaml = format!("name: Gas\namount: {yaml}\nday_paid: 6\n");
+            serde_yaml::from_str::<Payment>(&yaml)
+                .unwrap()
+                .amount
+                .to_string()
+        };
+
+        assert_eq!(amount("20"), "20.00");
+        assert_eq!(amount("20.5"), "20.50");
+        assert_eq!(amount("'20.00'"), "20.00");
+        assert_eq!(amount("'20.125'"), "20.125");
+    }
+
+    #[test]
+    fn runway_at_average_daily_spend() {
+        let payments = vec![Payment::new("Rent".to_owned(), Decimal::new(31000, 2), 1)];
+        let today = NaiveDate::from_str("2023-01-10").unwrap();
+
+        let runway = manager(Decimal::new(5500, 2), 1, payments).runway_days(&today);
+
+        assert_eq!(runway, Some(Decimal::new(5, 0)));
+    }
+
+    #[test]
+    fn runway_without_bills() {
+        let today = NaiveDate::from_str("2023-01-10").unwrap();
+
+        assert_eq!(
+            manager(Decimal::ONE_HUNDRED, 1, vec![]).runway_days(&today),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_date_walks_across_cycles() {
+        let payments = vec![
+            Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+            Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3),
+        ];
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+
+        let payment_manager = manager(Decimal::new(5000, 2), 18, payments.clone());
+        assert_eq!(
+            payment_manager.zero_date(&today, 12),
+            Some(NaiveDate::from_str("2023-03-03").unwrap())
+        );
+
+        let payment_manager = manager(Decimal::new(100000, 2), 18, payments);
+        assert_eq!(payment_manager.zero_date(&today, 12), None);
+    }
+
+    #[test]
+    fn show_description_when_set() {
+        let payment = Payment {
+            description: Some("Fixed until March".to_owned()),
+            ..Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6)
+        };
+
+        assert_eq!(
+            payment.to_string(),
+            "Bill: Gas\nAmount: £20.00\nDay paid: 6\nDescription: Fixed until March"
+        );
+    }
+
+    #[test]
+    fn prorate_amount_changed_mid_cycle() {
+        let water = Payment {
+            change: Some(AmountChange {
+                previous: Decimal::new(1000, 2),
+                effective: NaiveDate::from_str("2023-01-29").unwrap(),
+            }),
+            ..Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3)
+        };
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let payment_manager = manager(Decimal::new(10000, 2), 18, vec![water]);
+
+        assert_money_eq(payment_manager.remaining_balance(&today), "80.00");
+        assert_money_eq(
+            payment_manager.with_prorate(true).remaining_balance(&today),
+            "83.55",
+        );
+    }
+
+    #[test]
+    fn prorate_ignores_changes_outside_the_cycle() {
+        let water = |effective| Payment {
+            change: Some(AmountChange {
+                previous: Decimal::new(1000, 2),
+                effective: NaiveDate::from_str(effective).unwrap(),
+            }),
+            ..Payment::new("Water".to_owned(), Decimal::new(2000, 2), 3)
+        };
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let remaining = |effective| {
+            manager(Decimal::new(10000, 2), 18, vec![water(effective)])
+                .with_prorate(true)
+                .remaining_balance(&today)
+        };
+
+        assert_money_eq(remaining("2023-01-02"), "80.00");
+        assert_money_eq(remaining("2023-03-01"), "90.00");
+    }
+
+    fn shared(amount: Decimal, share_percent: Decimal) -> Payment {
+        Payment {
+            share_percent: Some(share_percent),
+            ..Payment::new("Rent".to_owned(), amount, 1)
+        }
+    }
+
+    #[test]
+    fn half_share_of_a_bill() {
+        let rent = shared(Decimal::new(3555, 2), Decimal::new(50, 0));
+        assert_money_eq(rent.share(), "17.78");
+
+        let payment_manager = manager(Decimal::new(10000, 2), 18, vec![rent]);
+        let remaining =
+            payment_manager.remaining_balance(&NaiveDate::from_str("2023-01-19").unwrap());
+        assert_money_eq(remaining, "82.22");
+    }
+
+    #[test]
+    fn third_share_of_a_bill() {
+        let rent = shared(Decimal::new(1055, 2), Decimal::new(333, 1));
+        assert_money_eq(rent.share(), "3.51");
+        assert_money_eq(rent.monthly_cost(), "3.51");
+
+        let config = Config {
+            payments: vec![shared(Decimal::new(1055, 2), Decimal::new(101, 0))],
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn retain_active_excludes_inactive_payments() {
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let payment = |name: &str| Payment::new(name.to_owned(), Decimal::new(1000, 2), 6);
+        let mut archived = payment("Archived");
+        archived.archived = true;
+        let mut not_started = payment("NotStarted");
+        not_started.start_date = NaiveDate::from_ymd_opt(2023, 1, 20);
+        let mut ended = payment("Ended");
+        ended.end_date = NaiveDate::from_ymd_opt(2023, 1, 18);
+        let mut ends_today = payment("EndsToday");
+        ends_today.start_date = NaiveDate::from_ymd_opt(2022, 1, 1);
+        ends_today.end_date = Some(today);
+        let payments = [payment("Gas"), archived, not_started, ended, ends_today];
+
+        let names: Vec<&str> = payments
+            .retain_active(&today)
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["Gas", "EndsToday"]);
+    }
+
+    #[test]
+    fn remaining_balance_sums_whole_pence() {
+        let mut payments: Payments = (0..30)
+            .map(|i| Payment::new(format!("Bill {i}"), Decimal::new(1, 1), 28))
+            .collect();
+        payments.push(Payment::new("Gas".to_owned(), Decimal::new(20_455, 3), 28));
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let payment_manager = manager(Decimal::new(2346, 2), 18, payments);
+
+        assert_eq!(
+            payment_manager.remaining_balance(&today),
+            Decimal::new(0, 2)
+        );
+        assert_eq!(payment_manager.report(&today).remaining, Decimal::new(0, 2));
+    }
+
+    #[test]
+    fn remaining_balance_ignores_ended_payments() {
+        let mut payments = vec![Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28)];
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let with_phone = manager(Decimal::ONE_HUNDRED, 18, payments.clone());
+        payments[0].end_date = NaiveDate::from_ymd_opt(2023, 1, 1);
+        let ended = manager(Decimal::ONE_HUNDRED, 18, payments);
+
+        assert_money_eq(with_phone.remaining_balance(&today), "90.00");
+        assert_money_eq(ended.remaining_balance(&today), "100.00");
+    }
+
+    #[test]
+    fn payment_fields_serialise_in_a_stable_order() {
+        let payment = Payment {
+            category: Some("Energy".to_owned()),
+            frequency: Frequency::SemiMonthly {
+                first: 1,
+                second: 15,
+            },
+            archived: true,
+            weekend_rule: WeekendRule::FollowingMonday,
+            description: Some("Fixed tariff".to_owned()),
+            change: Some(AmountChange {
+                previous: Decimal::new(1800, 2),
+                effective: NaiveDate::from_str("2023-01-01").unwrap(),
+            }),
+            share_percent: Some(Decimal::new(50, 0)),
+            start_date: NaiveDate::from_ymd_opt(2022, 1, 1),
+            end_date: NaiveDate::from_ymd_opt(2024, 1, 1),
+            reset_day: Some(1),
+            ..Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6)
+        };
+
+        let serde_yaml::Value::Mapping(fields) = serde_yaml::to_value(&payment).unwrap() else {
+            panic!("expected a mapping");
+        };
+        let keys: Vec<&str> = fields
+            .keys()
+            .filter_map(serde_yaml::Value::as_str)
+            .collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                "name",
+                "amount",
+                "day_paid",
+                "category",
+                "frequency",
+                "archived",
+                "weekend_rule",
+                "description",
+                "change",
+                "share_percent",
+                "start_date",
+                "end_date",
+                "reset_day",
+            ]
+        );
+        let unset =
+            serde_yaml::to_string(&Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6));
+        assert_eq!(unset.unwrap(), "name: Gas\namount: '20.00'\nday_paid: 6\n");
+    }
+
+    #[test]
+    fn payment_rebased_on_its_own_reset_day() {
+        let today = NaiveDate::from_str("2023-01-25").unwrap();
+        let payments = |card_reset_day| {
+            vec![
+                Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 20),
+                Payment {
+                    reset_day: card_reset_day,
+                    ..Payment::new("Card".to_owned(), Decimal::new(1000, 2), 20)
+                },
+                Payment::new("Water".to_owned(), Decimal::new(1000, 2), 3),
+            ]
+        };
+        let remaining = |card_reset_day| {
+            manager(Decimal::ONE_HUNDRED, 18, payments(card_reset_day)).remaining_balance(&today)
+        };
+
+        assert_money_eq(remaining(None), "90.00");
+        assert_money_eq(remaining(Some(21)), "80.00");
+        assert_money_eq(remaining(Some(18)), "90.00");
+    }
+
+    #[test]
+    fn payment_reset_day_must_be_in_range() {
+        let config = Config {
+            payments: vec![Payment {
+                reset_day: Some(31),
+                ..Payment::new("Card".to_owned(), Decimal::new(1000, 2), 20)
+            }],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Card: reset day 31 not in range 1-28"
+        );
+    }
+
+    #[test]
+    fn forecasts_skip_bills_outside_their_dates() {
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let gas = Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6);
+        let starts_in_march = Payment {
+            start_date: NaiveDate::from_ymd_opt(2023, 3, 1),
+            ..Payment::new("Gym".to_owned(), Decimal::new(1000, 2), 6)
+        };
+        let ended = Payment {
+            end_date: NaiveDate::from_ymd_opt(2023, 1, 31),
+            ..Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 6)
+        };
+
+        let forecast = manager(Decimal::ZERO, 18, vec![gas.clone(), starts_in_march])
+            .monthly_forecast(&today, 2);
+        let zero_date = manager(Decimal::new(2500, 2), 18, vec![gas, ended]).zero_date(&today, 3);
+
+        assert_money_eq(forecast[0].bills, "20.00");
+        assert_money_eq(forecast[1].bills, "30.00");
+        assert_eq!(zero_date, NaiveDate::from_ymd_opt(2023, 3, 6));
+    }
+
+    #[test]
+    fn skipped_days_are_left_out_of_forecasts() {
+        let payments = vec![
+            Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6),
+            Payment::new("Gym".to_owned(), Decimal::new(3000, 2), 30),
+        ];
+        let today = NaiveDate::from_str("2023-01-19").unwrap();
+        let forecast = |day_strategy| {
+            PaymentManager::new(Decimal::ZERO, 18, payments.clone(), day_strategy)
+                .with_income(Decimal::new(10000, 2))
+        };
+
+        let skip = forecast(DayStrategy::Skip);
+        let bills: Vec<Decimal> = skip
+            .monthly_forecast(&today, 2)
+            .iter()
+            .map(|row| row.bills)
+            .collect();
+        assert_eq!(bills, vec![Decimal::new(2000, 2), Decimal::new(5000, 2)]);
+        assert_eq!(
+            skip.projection(&today, 2),
+            vec![
+                Decimal::new(-5000, 2),
+                Decimal::new(3000, 2),
+                Decimal::new(8000, 2)
+            ]
+        );
+        assert_money_eq(
+            forecast(DayStrategy::Clamp).monthly_forecast(&today, 1)[0].bills,
+            "50.00",
+        );
+    }
 }
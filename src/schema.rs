@@ -0,0 +1,353 @@
+use std::ops::RangeInclusive;
+
+use serde_json::{json, Value};
+
+use crate::payment::{CONFIG_DAYS_RANGE, DAYS_PAID_RANGE};
+
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Amounts are stored as strings by default but numbers are also accepted. Amounts with more
+/// than two decimal places load, and are flagged by `doctor --precision-check`.
+fn amount_schema(description: &str) -> Value {
+    json!({
+        "description": description,
+        "oneOf": [
+            { "type": "string", "pattern": r"^\d+(\.\d+)?$" },
+            { "type": "number", "minimum": 0 },
+        ],
+    })
+}
+
+/// An amount that can be negative, such as a balance.
+fn signed_amount_schema(description: &str) -> Value {
+    json!({
+        "description": description,
+        "oneOf": [
+            { "type": "string", "pattern": r"^-?\d+(\.\d+)?$" },
+            { "type": "number" },
+        ],
+    })
+}
+
+fn day_schema(description: &str, days: &RangeInclusive<isize>) -> Value {
+    json!({
+        "description": description,
+        "type": "integer",
+        "minimum": days.start(),
+        "maximum": days.end(),
+    })
+}
+
+fn separator_schema(description: &str) -> Value {
+    json!({
+        "description": description,
+        "type": "string",
+        "minLength": 1,
+        "maxLength": 1,
+    })
+}
+
+fn frequency_schema() -> Value {
+    json!({
+        "description": "How often the bill is paid, monthly by default.",
+        "oneOf": [
+            {
+                "type": "object",
+                "properties": { "type": { "const": "Monthly" } },
+                "required": ["type"],
+                "additionalProperties": false,
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": { "const": "SemiMonthly" },
+                    "first": day_schema("First day of the month the bill is paid.", &DAYS_PAID_RANGE),
+                    "second": day_schema("Second day of the month the bill is paid.", &DAYS_PAID_RANGE),
+                },
+                "required": ["type", "first", "second"],
+                "additionalProperties": false,
+            },
+        ],
+    })
+}
+
+fn payment_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "description": "Name of the bill.", "type": "string" },
+            "amount": amount_schema("Amount the bill will be."),
+            "day_paid": day_schema("Day of the month the bill is paid.", &CONFIG_DAYS_RANGE),
+            "category": { "description": "Category used to group bills.", "type": "string" },
+            "frequency": frequency_schema(),
+            "archived": {
+                "description": "Archived bills are kept but otherwise ignored.",
+                "type": "boolean",
+            },
+            "weekend_rule": {
+                "description": "When a bill due on a weekend is actually taken.",
+                "enum": ["None", "PrecedingFriday", "FollowingMonday"],
+            },
+            "description": {
+                "description": "Notes about the bill, kept when the config is stored.",
+                "type": "string",
+            },
+            "share_percent": {
+                "description": "Percentage of a shared bill that you pay.",
+                "type": ["string", "number"],
+            },
+            "change": {
+                "description": "Last change to the amount, used to prorate the cycle it took effect in.",
+                "type": "object",
+                "properties": {
+                    "previous": amount_schema("Amount paid before the change."),
+                    "effective": {
+                        "description": "First day the new amount applies.",
+                        "type": "string",
+                        "format": "date",
+                    },
+                },
+                "required": ["previous", "effective"],
+                "additionalProperties": false,
+            },
+            "start_date": {
+                "description": "First day the bill is paid.",
+                "type": "string",
+                "format": "date",
+            },
+            "end_date": {
+                "description": "Last day the bill is paid.",
+                "type": "string",
+                "format": "date",
+            },
+            "reset_day": day_schema(
+                "Day this bill's own cycle resets, in place of the global one.",
+                &DAYS_PAID_RANGE,
+            ),
+        },
+        "required": ["name", "amount", "day_paid"],
+        "additionalProperties": false,
+    })
+}
+
+/// JSON Schema describing the config file, for editors to validate it against.
+pub fn config_schema() -> Value {
+    json!({
+        "$schema": SCHEMA_DRAFT,
+        "title": "balance config",
+        "type": "object",
+        "properties": {
+            "version": { "description": "Version of the config format.", "type": "integer", "minimum": 0 },
+            "payments": { "type": "array", "items": payment_schema() },
+            "rounding": { "enum": ["HalfUp", "HalfEven", "Down"] },
+            "currency_symbol": { "type": "string" },
+            "currency_code": {
+                "description": "Three letter currency code, overriding currency_symbol.",
+                "type": "string",
+                "pattern": "^[A-Za-z]{3}$",
+            },
+            "currency_position": { "enum": ["Prefix", "Suffix"] },
+            "grouped": { "type": "boolean" },
+            "group_separator": separator_schema("Separator between groups of thousands."),
+            "decimal_separator": separator_schema("Separator before the pence."),
+            "budget": amount_schema("Monthly budget for bills."),
+            "round_day_strategy": {
+                "description": "How bills due after the end of a short month are handled.",
+                "enum": ["Clamp", "Skip"],
+            },
+            "income": amount_schema("Monthly income, bills above it are warned about."),
+            "buffer": amount_schema("Amount kept aside from the balance shown by compute."),
+            "blackout_days": {
+                "description": "Days of the month bills can't be added or moved to.",
+                "type": "array",
+                "items": day_schema("A day bills can't be paid on.", &CONFIG_DAYS_RANGE),
+            },
+            "color_thresholds": {
+                "description": "Balances compute shows in red below low and green from high.",
+                "type": "object",
+                "properties": {
+                    "low": signed_amount_schema("Balances below this are red."),
+                    "high": signed_amount_schema("Balances from this up are green."),
+                },
+                "required": ["low", "high"],
+                "additionalProperties": false,
+            },
+            "date_format": { "description": "strftime format for dates.", "type": "string" },
+        },
+        "required": ["payments"],
+        "additionalProperties": false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment::example_config;
+
+    fn type_matches(value: &Value, name: &str) -> bool {
+        match name {
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            _ => false,
+        }
+    }
+
+    /// Where `value` breaks `schema`, checking only the keywords this schema uses. Patterns and
+    /// formats aren't checked.
+    fn violations(value: &Value, schema: &Value, path: &str) -> Vec<String> {
+        let mut found = vec![];
+        if let Some(options) = schema["oneOf"].as_array() {
+            let matching = options
+                .iter()
+                .filter(|option| violations(value, option, path).is_empty())
+                .count();
+            if matching != 1 {
+                found.push(format!("{path}: matches {matching} of oneOf"));
+            }
+        }
+        if schema.get("const").is_some_and(|c| c != value) {
+            found.push(format!("{path}: {value} isn't {}", schema["const"]));
+        }
+        if let Some(options) = schema["enum"].as_array() {
+            if !options.contains(value) {
+                found.push(format!("{path}: {value} isn't one of {options:?}"));
+            }
+        }
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(name) => vec![name],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(value, t)) {
+            found.push(format!("{path}: {value} isn't {types:?}"));
+        }
+        if let (Some(number), Some(minimum)) = (value.as_f64(), schema["minimum"].as_f64()) {
+            if number < minimum {
+                found.push(format!("{path}: {number} is below {minimum}"));
+            }
+        }
+        if let (Some(number), Some(maximum)) = (value.as_f64(), schema["maximum"].as_f64()) {
+            if number > maximum {
+                found.push(format!("{path}: {number} is above {maximum}"));
+            }
+        }
+        if let Some(text) = value.as_str() {
+            let length = text.chars().count() as u64;
+            if schema["minLength"].as_u64().is_some_and(|min| length < min)
+                || schema["maxLength"].as_u64().is_some_and(|max| length > max)
+            {
+                found.push(format!("{path}: `{text}` has the wrong length"));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for (key, field) in object {
+                let path = format!("{path}.{key}");
+                match schema["properties"].get(key) {
+                    Some(field_schema) => found.extend(violations(field, field_schema, &path)),
+                    None if schema["additionalProperties"] == false => {
+                        found.push(format!("{path}: not in the schema"));
+                    }
+                    None => {}
+                }
+            }
+            for key in schema["required"].as_array().into_iter().flatten() {
+                if !object.contains_key(key.as_str().unwrap()) {
+                    found.push(format!("{path}: missing {key}"));
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            for (i, item) in items.iter().enumerate() {
+                found.extend(violations(item, &schema["items"], &format!("{path}[{i}]")));
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn stored_config_matches_schema() {
+        let schema = config_schema();
+        let config = serde_json::to_value(example_config()).unwrap();
+
+        assert_eq!(violations(&config, &schema, "config"), Vec::<String>::new());
+
+        let keys = |value: &Value| -> Vec<String> {
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        let payment_schema = &schema["properties"]["payments"]["items"];
+        assert_eq!(keys(&config), keys(&schema["properties"]));
+        assert_eq!(
+            keys(&config["payments"][0]),
+            keys(&payment_schema["properties"])
+        );
+    }
+
+    #[test]
+    fn schema_rejects_bad_values() {
+        let schema = config_schema();
+        let mut config = serde_json::to_value(example_config()).unwrap();
+        config["payments"][0]["day_paid"] = json!(32);
+        config["payments"][0]["categroy"] = json!("Home");
+
+        assert_eq!(
+            violations(&config, &schema, "config"),
+            vec![
+                "config.payments[0].categroy: not in the schema",
+                "config.payments[0].day_paid: 32 is above 31",
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_describes_payment_constraints() {
+        let schema = config_schema();
+        let payment = &schema["properties"]["payments"]["items"];
+
+        assert_eq!(payment["required"], json!(["name", "amount", "day_paid"]));
+        assert_eq!(payment["properties"]["day_paid"]["minimum"], 1);
+        assert_eq!(payment["properties"]["day_paid"]["maximum"], 31);
+        assert_eq!(payment["properties"]["reset_day"]["maximum"], 28);
+        assert_eq!(
+            payment["properties"]["amount"]["oneOf"][0]["pattern"],
+            r"^\d+(\.\d+)?$"
+        );
+        assert_eq!(payment["properties"]["amount"]["oneOf"][1]["minimum"], 0);
+    }
+
+    #[test]
+    fn schema_accepts_configs_the_app_loads() {
+        let schema = config_schema();
+        let mut config = serde_json::to_value(example_config()).unwrap();
+        config["payments"][0]["day_paid"] = json!(31);
+        config["payments"][0]["amount"] = json!("20.4550");
+        config["color_thresholds"] = json!({"low": "-50.00", "high": "100.00"});
+        let yaml = serde_yaml::to_string(&config).unwrap();
+
+        let loaded: crate::payment::Config = serde_yaml::from_str(&yaml).unwrap();
+        loaded.validate().unwrap();
+        assert_eq!(violations(&config, &schema, "config"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn schema_lists_config_properties() {
+        let schema = config_schema();
+        let properties = schema["properties"].as_object().unwrap();
+
+        for key in [
+            "payments",
+            "rounding",
+            "currency_symbol",
+            "budget",
+            "date_format",
+        ] {
+            assert!(properties.contains_key(key), "missing {key}");
+        }
+        assert_eq!(schema["additionalProperties"], false);
+    }
+}
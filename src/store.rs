@@ -0,0 +1,285 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::payment::{self, Config};
+
+/// Somewhere the config is loaded from and stored back to.
+pub trait ConfigStore {
+    fn load(&self) -> Result<Config>;
+    fn store(&mut self, config: &Config) -> Result<()>;
+    fn edit(&mut self) -> Result<()>;
+    /// Keeps a copy of the currently stored config.
+    fn backup(&mut self) -> Result<()>;
+    /// When the stored config last changed, if the store records it.
+    fn modified(&self) -> Result<Option<SystemTime>> {
+        Ok(None)
+    }
+    /// File to cache `compute` results in, if the store has somewhere to keep one.
+    fn cache_path(&self) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+    /// Whether loading rejects unknown keys, so results cached by a lenient load aren't reused
+    /// when a strict one was asked for.
+    fn strict(&self) -> bool {
+        false
+    }
+    /// Locks the stored config until the returned lock is dropped, if the store can be shared.
+    fn lock(&self) -> Result<Option<ConfigLock>> {
+        Ok(None)
+    }
+}
+
+/// Stores the config in the user's config directory using confy.
+pub struct ConfyStore {
+    /// Reject unknown keys when loading the config.
+    pub strict: bool,
+}
+
+impl ConfigStore for ConfyStore {
+    fn load(&self) -> Result<Config> {
+        payment::get_config(self.strict)
+    }
+
+    fn store(&mut self, config: &Config) -> Result<()> {
+        payment::store_config(config)
+    }
+
+    fn edit(&mut self) -> Result<()> {
+        payment::edit_config()
+    }
+
+    fn backup(&mut self) -> Result<()> {
+        payment::backup_config()
+    }
+
+    fn modified(&self) -> Result<Option<SystemTime>> {
+        payment::config_modified()
+    }
+
+    fn cache_path(&self) -> Result<Option<PathBuf>> {
+        payment::compute_cache_path().map(Some)
+    }
+
+    fn strict(&self) -> bool {
+        self.strict
+    }
+
+    fn lock(&self) -> Result<Option<ConfigLock>> {
+        ConfigLock::acquire(&payment::config_lock_path()?).map(Some)
+    }
+}
+
+/// An advisory lock on a file, held until dropped, so that two processes don't load, change and
+/// store the config at the same time.
+#[derive(Debug)]
+pub struct ConfigLock {
+    _file: File,
+}
+
+impl ConfigLock {
+    /// Locks `path`, creating it if needed, or errors if another process holds the lock.
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(anyhow!(
+                "the config is in use by another balance process, try again once it finishes"
+            )),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// Calls `recompute` once, then again each time `modified` reports a different time after
+/// `wait` returns, stopping when `wait` returns false.
+///
+/// This polls rather than subscribing to file system events, so a change is picked up within one
+/// `wait`. A failed recompute, such as one reading a config still being saved, is reported and
+/// watching carries on, as does a failure to read the modified time, such as while an editor
+/// replaces the file.
+pub fn watch(
+    mut modified: impl FnMut() -> Result<Option<SystemTime>>,
+    mut recompute: impl FnMut() -> Result<()>,
+    mut wait: impl FnMut() -> bool,
+) -> Result<()> {
+    let mut report_errors = || {
+        if let Err(e) = recompute() {
+            eprintln!("error: {e}");
+        }
+    };
+    let mut last = modified()?;
+    report_errors();
+    while wait() {
+        let current = match modified() {
+            Ok(current) => current,
+            Err(e) => {
+                eprintln!("warning: couldn't check the config for changes: {e}");
+                continue;
+            }
+        };
+        if current != last {
+            last = current;
+            report_errors();
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the config in memory, so commands can be tested without touching the filesystem.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    pub config: Config,
+    pub backup: Option<Config>,
+    /// Number of times the config has been stored.
+    pub stores: usize,
+    /// Number of times the config has been locked.
+    pub locks: std::cell::Cell<usize>,
+    pub modified: Option<SystemTime>,
+    pub cache_path: Option<PathBuf>,
+    pub strict: bool,
+}
+
+#[cfg(test)]
+impl ConfigStore for MemoryStore {
+    fn load(&self) -> Result<Config> {
+        Ok(self.config.clone())
+    }
+
+    fn store(&mut self, config: &Config) -> Result<()> {
+        self.config = config.clone();
+        self.stores += 1;
+        Ok(())
+    }
+
+    fn edit(&mut self) -> Result<()> {
+        Err(anyhow::anyhow!("an in-memory config can't be edited"))
+    }
+
+    fn backup(&mut self) -> Result<()> {
+        self.backup = Some(self.config.clone());
+        Ok(())
+    }
+
+    fn modified(&self) -> Result<Option<SystemTime>> {
+        Ok(self.modified)
+    }
+
+    fn cache_path(&self) -> Result<Option<PathBuf>> {
+        Ok(self.cache_path.clone())
+    }
+
+    fn strict(&self) -> bool {
+        self.strict
+    }
+
+    fn lock(&self) -> Result<Option<ConfigLock>> {
+        self.locks.set(self.locks.get() + 1);
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_lock_blocks_second_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spend.yml.lock");
+
+        let first = ConfigLock::acquire(&path).unwrap();
+        let error = ConfigLock::acquire(&path).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("in use by another balance process"));
+
+        drop(first);
+        assert!(ConfigLock::acquire(&path).is_ok());
+    }
+
+    #[test]
+    fn watch_recomputes_on_change() {
+        let start = SystemTime::UNIX_EPOCH;
+        let later = start + std::time::Duration::from_secs(60);
+        let mut times = vec![start, start, start, later, later].into_iter();
+        let mut recomputes = 0;
+        let mut polls = 0;
+
+        watch(
+            || Ok(times.next()),
+            || {
+                recomputes += 1;
+                Ok(())
+            },
+            || {
+                polls += 1;
+                polls < 5
+            },
+        )
+        .unwrap();
+
+        assert_eq!(recomputes, 2);
+    }
+
+    #[test]
+    fn watch_carries_on_after_a_failed_recompute() {
+        let start = SystemTime::UNIX_EPOCH;
+        let time = |secs| Some(start + std::time::Duration::from_secs(secs));
+        let mut times = vec![time(0), time(0), time(60), time(120)].into_iter();
+        let mut recomputes = 0;
+        let mut polls = 0;
+
+        watch(
+            || Ok(times.next().flatten()),
+            || {
+                recomputes += 1;
+                if recomputes == 2 {
+                    Err(anyhow!("config is half written"))
+                } else {
+                    Ok(())
+                }
+            },
+            || {
+                polls += 1;
+                polls < 4
+            },
+        )
+        .unwrap();
+
+        assert_eq!(recomputes, 3);
+    }
+
+    #[test]
+    fn watch_carries_on_after_a_failed_modified_check() {
+        let start = SystemTime::UNIX_EPOCH;
+        let time = |secs| Ok(Some(start + std::time::Duration::from_secs(secs)));
+        let mut times = vec![time(0), Err(anyhow!("config was removed")), time(60)].into_iter();
+        let mut recomputes = 0;
+        let mut polls = 0;
+
+        watch(
+            || times.next().unwrap(),
+            || {
+                recomputes += 1;
+                Ok(())
+            },
+            || {
+                polls += 1;
+                polls < 3
+            },
+        )
+        .unwrap();
+
+        assert_eq!(recomputes, 2);
+    }
+}
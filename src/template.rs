@@ -0,0 +1,143 @@
+use crate::{money::MoneyFormat, payment::Payment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Amount,
+    Day,
+    Category,
+}
+
+const FIELDS: &[(&str, Field)] = &[
+    ("name", Field::Name),
+    ("amount", Field::Amount),
+    ("day", Field::Day),
+    ("category", Field::Category),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+/// A format string such as `{name} costs {amount}`, expanded for each payment. Braces are written
+/// as `{{` and `}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(format!(
+                                    "unclosed placeholder `{{{placeholder}`, write `{{{{` for a \
+                                     literal brace"
+                                ))
+                            }
+                        }
+                    }
+                    let field = FIELDS
+                        .iter()
+                        .find(|(name, _)| *name == placeholder)
+                        .map(|(_, field)| *field)
+                        .ok_or_else(|| {
+                            let known: Vec<String> = FIELDS
+                                .iter()
+                                .map(|(name, _)| format!("{{{name}}}"))
+                                .collect();
+                            format!(
+                                "unknown placeholder `{{{placeholder}}}`, expected one of {}",
+                                known.join(", ")
+                            )
+                        })?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a literal brace".to_owned()),
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, payment: &Payment, money: &MoneyFormat) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Field(Field::Name) => payment.name.clone(),
+                Segment::Field(Field::Amount) => money.format(payment.amount),
+                Segment::Field(Field::Day) => payment.days_paid_text(),
+                Segment::Field(Field::Category) => payment.category.clone().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn gas() -> Payment {
+        Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6).with_category("Energy")
+    }
+
+    #[test]
+    fn render_templates() {
+        let money = MoneyFormat::default();
+
+        let template = Template::parse("{name}: {amount} on day {day}").unwrap();
+        assert_eq!(template.render(&gas(), &money), "Gas: £20.00 on day 6");
+
+        let template = Template::parse("{{{category}}} {name}").unwrap();
+        assert_eq!(template.render(&gas(), &money), "{Energy} Gas");
+    }
+
+    #[test]
+    fn reject_unknown_placeholders() {
+        assert_eq!(
+            Template::parse("{name} {cost}").unwrap_err(),
+            "unknown placeholder `{cost}`, expected one of {name}, {amount}, {day}, {category}"
+        );
+        assert!(Template::parse("{name}}").is_err());
+    }
+
+    #[test]
+    fn reject_unclosed_placeholders() {
+        assert_eq!(
+            Template::parse("{name} on {day").unwrap_err(),
+            "unclosed placeholder `{day`, write `{{` for a literal brace"
+        );
+        assert!(Template::parse("{").is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{
+    money::MoneyFormat,
+    payment::{self, DayStrategy, PaymentManager, Payments, PaymentsExt},
+};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HELP: &str = "j/k move, a AMOUNT, d DAY, b BALANCE, s save, q quit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    SetAmount(Decimal),
+    SetDay(isize),
+    SetBalance(Decimal),
+    Save,
+    Quit,
+    ForceQuit,
+}
+
+/// What the caller should do after an action is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Redraw,
+    Save,
+    Quit,
+}
+
+pub fn parse_action(line: &str) -> Result<Action> {
+    let line = line.trim();
+    let (command, value) = line
+        .split_once(' ')
+        .map_or((line, ""), |(c, v)| (c, v.trim()));
+    let decimal = || {
+        value
+            .parse::<Decimal>()
+            .map_err(|_| anyhow!("`{value}` isn't a Decimal"))
+    };
+
+    match command {
+        "k" | "up" => Ok(Action::Up),
+        "j" | "down" => Ok(Action::Down),
+        "a" => Ok(Action::SetAmount(decimal()?)),
+        "d" => Ok(Action::SetDay(
+            value
+                .parse()
+                .map_err(|_| anyhow!("`{value}` isn't an integer"))?,
+        )),
+        "b" => Ok(Action::SetBalance(decimal()?)),
+        "s" => Ok(Action::Save),
+        "q" => Ok(Action::Quit),
+        "q!" => Ok(Action::ForceQuit),
+        _ => Err(anyhow!("unknown command `{command}`, {HELP}")),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TuiState {
+    /// The bills being edited, in the order they are stored.
+    pub payments: Payments,
+    /// Positions in `payments` in the order the bills are shown, sorted by name.
+    order: Vec<usize>,
+    /// The bills as they were loaded or last saved, to tell which fields have been edited.
+    saved_payments: Payments,
+    /// Row of the selected bill in the shown order.
+    pub selected: usize,
+    pub balance: Option<Decimal>,
+    pub reset_day: isize,
+    pub day_strategy: DayStrategy,
+    /// Whether there are edits that haven't been saved.
+    pub dirty: bool,
+    pub message: Option<String>,
+}
+
+impl TuiState {
+    pub fn new(payments: Payments, reset_day: isize, day_strategy: DayStrategy) -> Self {
+        let mut order: Vec<usize> = (0..payments.len()).collect();
+        order.sort_by(|&a, &b| payments[a].cmp(&payments[b]));
+        Self {
+            saved_payments: payments.clone(),
+            payments,
+            order,
+            selected: 0,
+            balance: None,
+            reset_day,
+            day_strategy,
+            dirty: false,
+            message: None,
+        }
+    }
+
+    pub fn update(&mut self, action: Action) -> Result<Effect> {
+        self.message = None;
+        match action {
+            Action::Up => self.selected = self.selected.saturating_sub(1),
+            Action::Down => {
+                self.selected = (self.selected + 1).min(self.order.len().saturating_sub(1));
+            }
+            Action::SetAmount(amount) => {
+                let amount = payment::validate_amount(amount)?;
+                self.selected_mut()?.amount = amount;
+                self.dirty = true;
+            }
+            Action::SetDay(day) => {
+                let day = payment::validate_day_paid(day)?;
+                self.selected_mut()?.day_paid = day;
+                self.dirty = true;
+            }
+            Action::SetBalance(balance) => self.balance = Some(balance),
+            Action::Save => return Ok(Effect::Save),
+            Action::Quit if self.dirty => {
+                self.message = Some("There are unsaved changes, save or use q! to quit".to_owned());
+            }
+            Action::Quit | Action::ForceQuit => return Ok(Effect::Quit),
+        }
+        Ok(Effect::Redraw)
+    }
+
+    /// Records how a save went, only clearing the unsaved changes once they were stored.
+    pub fn saved(&mut self, result: Result<bool>) {
+        self.message = Some(match result {
+            Ok(true) => {
+                self.saved_payments = self.payments.clone();
+                self.dirty = false;
+                "Saved".to_owned()
+            }
+            Ok(false) => "Not saved".to_owned(),
+            Err(e) => format!("error: couldn't save: {e}"),
+        });
+    }
+
+    fn selected_mut(&mut self) -> Result<&mut payment::Payment> {
+        self.order
+            .get(self.selected)
+            .and_then(|&i| self.payments.get_mut(i))
+            .ok_or_else(|| anyhow!("there are no bills to edit"))
+    }
+
+    /// Applies the amounts and days edited since the last save to `payments`, matching bills by
+    /// name, so changes made elsewhere in the meantime are kept.
+    pub fn apply_edits(&self, payments: &mut Payments) -> Result<()> {
+        for (edited, saved) in self.payments.iter().zip(&self.saved_payments) {
+            if edited.amount == saved.amount && edited.day_paid == saved.day_paid {
+                continue;
+            }
+            let i = payments
+                .position_of(&saved.name)
+                .ok_or_else(|| anyhow!("{} was removed while it was being edited", saved.name))?;
+            if edited.amount != saved.amount {
+                payments[i].amount = edited.amount;
+            }
+            if edited.day_paid != saved.day_paid {
+                payments[i].day_paid = edited.day_paid;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remaining(&self, today: &NaiveDate) -> Option<Decimal> {
+        self.balance.map(|balance| {
+            PaymentManager::new(
+                balance,
+                self.reset_day,
+                self.payments.clone(),
+                self.day_strategy,
+            )
+            .remaining_balance(today)
+        })
+    }
+
+    pub fn render(&self, today: &NaiveDate, money: &MoneyFormat) -> Vec<String> {
+        let header = match (self.balance, self.remaining(today)) {
+            (Some(balance), Some(remaining)) => format!(
+                "Balance: {}, remaining: {}",
+                money.format(balance),
+                money.format(remaining)
+            ),
+            _ => "Balance: not set, enter b BALANCE".to_owned(),
+        };
+
+        let mut lines = vec![header, String::new()];
+        lines.extend(self.order.iter().enumerate().map(|(row, &i)| {
+            let p = &self.payments[i];
+            let marker = if row == self.selected { ">" } else { " " };
+            format!(
+                "{marker} {} {}, day paid: {}",
+                p.name,
+                money.format(p.amount),
+                p.day_paid
+            )
+        }));
+        lines.push(String::new());
+        lines.push(HELP.to_owned());
+        lines.extend(self.message.clone());
+        lines
+    }
+}
+
+/// Runs the dashboard until the user quits, calling `save` with the state whenever they save.
+///
+/// Each command is read with `read_line`, rather than holding on to stdin, so `save` can ask for
+/// confirmation on it too. `save` returns whether the bills were stored.
+pub fn run(
+    state: &mut TuiState,
+    today: &NaiveDate,
+    money: &MoneyFormat,
+    read_line: &mut impl FnMut(&mut String) -> std::io::Result<usize>,
+    output: &mut impl Write,
+    save: &mut impl FnMut(&TuiState) -> Result<bool>,
+) -> Result<()> {
+    loop {
+        write!(output, "{CLEAR_SCREEN}")?;
+        for line in state.render(today, money) {
+            writeln!(output, "{line}")?;
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let effect = parse_action(&line).and_then(|action| state.update(action));
+        match effect {
+            Ok(Effect::Redraw) => {}
+            Ok(Effect::Save) => {
+                let result = save(state);
+                state.saved(result);
+            }
+            Ok(Effect::Quit) => return Ok(()),
+            Err(e) => state.message = Some(format!("error: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+
+    use super::*;
+    use crate::payment::Payment;
+
+    fn state() -> TuiState {
+        TuiState::new(
+            vec![
+                Payment::new("Water".to_owned(), Decimal::new(1500, 2), 3),
+                Payment::new("Gas".to_owned(), Decimal::new(2000, 2), 6),
+            ],
+            18,
+            DayStrategy::Clamp,
+        )
+    }
+
+    #[test]
+    fn edit_selected_bill() {
+        let mut state = state();
+
+        state.update(Action::Down).unwrap();
+        state.update(Action::Down).unwrap();
+        state
+            .update(Action::SetAmount(Decimal::new(1800, 2)))
+            .unwrap();
+        state.update(Action::SetDay(4)).unwrap();
+
+        assert_eq!(state.selected, 1);
+        assert_eq!(state.payments[0].name, "Water");
+        assert_eq!(state.payments[0].amount, Decimal::new(1800, 2));
+        assert_eq!(state.payments[0].day_paid, 4);
+        assert!(state.dirty);
+    }
+
+    #[test]
+    fn invalid_edits_are_rejected() {
+        let mut state = state();
+
+        assert!(state.update(Action::SetDay(31)).is_err());
+        assert!(state
+            .update(Action::SetAmount(Decimal::new(-1, 0)))
+            .is_err());
+        assert!(!state.dirty);
+    }
+
+    #[test]
+    fn quitting_needs_a_save_or_force() {
+        let mut state = state();
+        state.update(Action::SetDay(4)).unwrap();
+
+        assert_eq!(state.update(Action::Quit).unwrap(), Effect::Redraw);
+        assert_eq!(state.update(Action::Save).unwrap(), Effect::Save);
+        assert!(state.dirty);
+        state.saved(Ok(true));
+        assert!(!state.dirty);
+        assert_eq!(state.update(Action::Quit).unwrap(), Effect::Quit);
+    }
+
+    #[test]
+    fn live_remaining_balance() {
+        let mut state = state();
+        let today = NaiveDate::from_ymd_opt(2023, 1, 31).unwrap();
+        assert_eq!(state.remaining(&today), None);
+
+        state
+            .update(Action::SetBalance(Decimal::ONE_HUNDRED))
+            .unwrap();
+        state
+            .update(Action::SetAmount(Decimal::new(3000, 2)))
+            .unwrap();
+
+        assert_eq!(
+            state.render(&today, &MoneyFormat::default())[0],
+            "Balance: £100.00, remaining: £55.00"
+        );
+    }
+
+    #[test]
+    fn run_saves_edits_from_input() {
+        let mut state = state();
+        let today = NaiveDate::from_ymd_opt(2023, 1, 31).unwrap();
+        let mut input: &[u8] = b"j\na 25.00\ns\nq\n";
+        let mut saved = vec![];
+
+        run(
+            &mut state,
+            &today,
+            &MoneyFormat::default(),
+            &mut |line| input.read_line(line),
+            &mut std::io::sink(),
+            &mut |state| {
+                saved = state.payments.clone();
+                Ok(true)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(saved[0].name, "Water");
+        assert_eq!(saved[0].amount, Decimal::new(2500, 2));
+    }
+
+    #[test]
+    fn failed_save_keeps_changes_unsaved() {
+        let mut state = state();
+        let today = NaiveDate::from_ymd_opt(2023, 1, 31).unwrap();
+        let mut input: &[u8] = b"a 25.00\ns\n";
+
+        run(
+            &mut state,
+            &today,
+            &MoneyFormat::default(),
+            &mut |line| input.read_line(line),
+            &mut std::io::sink(),
+            &mut |_| Err(anyhow!("disk full")),
+        )
+        .unwrap();
+
+        assert!(state.dirty);
+        assert_eq!(
+            state.message.as_deref(),
+            Some("error: couldn't save: disk full")
+        );
+    }
+
+    #[test]
+    fn bills_are_shown_by_name_and_kept_in_stored_order() {
+        let state = state();
+
+        let lines = state.render(
+            &NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            &MoneyFormat::default(),
+        );
+
+        assert!(lines[2].starts_with("> Gas"));
+        assert!(lines[3].starts_with("  Water"));
+        assert_eq!(state.payments[0].name, "Water");
+    }
+
+    #[test]
+    fn saving_applies_only_the_edits() {
+        let mut state = state();
+        state.update(Action::SetDay(4)).unwrap();
+        let mut latest = vec![
+            Payment::new("Phone".to_owned(), Decimal::new(1000, 2), 28),
+            Payment::new("Gas".to_owned(), Decimal::new(2200, 2), 6),
+            Payment::new("Water".to_owned(), Decimal::new(1600, 2), 3),
+        ];
+
+        state.apply_edits(&mut latest).unwrap();
+
+        let bills: Vec<(&str, Decimal, isize)> = latest
+            .iter()
+            .map(|p| (p.name.as_str(), p.amount, p.day_paid))
+            .collect();
+        assert_eq!(
+            bills,
+            vec![
+                ("Phone", Decimal::new(1000, 2), 28),
+                ("Gas", Decimal::new(2200, 2), 4),
+                ("Water", Decimal::new(1600, 2), 3),
+            ]
+        );
+        state.saved(Ok(true));
+        assert!(state.apply_edits(&mut vec![]).is_ok());
+    }
+
+    #[test]
+    fn editing_a_removed_bill_fails_to_save() {
+        let mut state = state();
+        state.update(Action::SetDay(4)).unwrap();
+
+        assert_eq!(
+            state.apply_edits(&mut vec![]).unwrap_err().to_string(),
+            "Gas was removed while it was being edited"
+        );
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            parse_action("a 12.50").unwrap(),
+            Action::SetAmount(Decimal::new(1250, 2))
+        );
+        assert_eq!(parse_action(" d 4 ").unwrap(), Action::SetDay(4));
+        assert_eq!(parse_action("q!").unwrap(), Action::ForceQuit);
+        assert!(parse_action("x").is_err());
+    }
+}
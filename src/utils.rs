@@ -1,4 +1,11 @@
-use chrono::{Datelike, NaiveDate};
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc, Weekday,
+};
+use rust_decimal::{Decimal, RoundingStrategy};
 
 pub const fn modulo(a: isize, b: isize) -> isize {
     ((a % b) + b) % b
@@ -23,8 +30,168 @@ pub fn days_in_month(date: &NaiveDate) -> isize {
     }
 }
 
+pub fn clamp_day(day: isize, days_in_month: isize) -> isize {
+    day.clamp(1, days_in_month)
+}
+
+/// Next date, after `date`, that a cycle starting on `reset_day` resets on.
+pub fn next_reset(date: &NaiveDate, reset_day: isize) -> NaiveDate {
+    let this_month = clamp_day(reset_day, days_in_month(date));
+    let start_of_month = date.with_day(1).unwrap();
+
+    let (month, reset_day) = if (date.day() as isize) < this_month {
+        (start_of_month, this_month)
+    } else {
+        let next_month = start_of_month + Months::new(1);
+        (next_month, clamp_day(reset_day, days_in_month(&next_month)))
+    };
+
+    month.with_day(reset_day as u32).unwrap()
+}
+
+/// Next date on or after `date` falling on `day`, clamped to the length of its month.
+pub fn next_due(date: &NaiveDate, day: isize) -> NaiveDate {
+    let this_month = clamp_day(day, days_in_month(date));
+    let start_of_month = date.with_day(1).unwrap();
+
+    let (month, day) = if (date.day() as isize) <= this_month {
+        (start_of_month, this_month)
+    } else {
+        let next_month = start_of_month + Months::new(1);
+        (next_month, clamp_day(day, days_in_month(&next_month)))
+    };
+
+    month.with_day(day as u32).unwrap()
+}
+
+/// Calendar date that `day` falls on in the bill cycle containing `date`.
+pub fn cycle_date(date: &NaiveDate, reset_day: isize, day: isize) -> NaiveDate {
+    let start_of_month = date.with_day(1).unwrap();
+    let after_reset = |d: &NaiveDate, day: isize| day >= clamp_day(reset_day, days_in_month(d));
+
+    let month = match (
+        after_reset(date, date.day() as isize),
+        after_reset(date, day),
+    ) {
+        (true, false) => start_of_month + Months::new(1),
+        (false, true) => start_of_month - Months::new(1),
+        _ => start_of_month,
+    };
+
+    month
+        .with_day(clamp_day(day, days_in_month(&month)) as u32)
+        .unwrap()
+}
+
+/// `day` with its English ordinal suffix, such as `1st` or `18th`.
+pub fn ordinal(day: isize) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{day}{suffix}")
+}
+
+pub fn is_weekend(date: &NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Weeks from the week containing `from` to the week containing `to`, with weeks starting on
+/// Monday.
+pub fn weeks_between(from: &NaiveDate, to: &NaiveDate) -> i64 {
+    let week_start = |d: &NaiveDate| *d - Duration::days(d.weekday().num_days_from_monday().into());
+    (week_start(to) - week_start(from)).num_weeks()
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub fn validate_date_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        Err(anyhow!("invalid date format `{format}`"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn format_date(date: &NaiveDate, format: &str) -> Result<String> {
+    validate_date_format(format)?;
+
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format))
+        .map_err(|_| anyhow!("date format `{format}` can't be used for dates"))?;
+    Ok(formatted)
+}
+
+pub fn current_date<Tz: TimeZone>(now: DateTime<Utc>, local: &Tz, utc: bool) -> NaiveDate {
+    if utc {
+        now.date_naive()
+    } else {
+        now.with_timezone(local).date_naive()
+    }
+}
+
+pub fn apply_percent(amount: Decimal, percent: Decimal) -> Decimal {
+    (amount * (Decimal::ONE_HUNDRED + percent) / Decimal::ONE_HUNDRED)
+        .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Number of single character insertions, deletions or substitutions needed to turn `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The candidate closest to `target`, ignoring case, if it is close enough to be a likely typo.
+pub fn closest_match<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let target = target.to_lowercase();
+    let max_distance = (target.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|c| (levenshtein(&target, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+/// Asserts `actual` equals the amount written in `expected`, which must have two decimal places.
+#[cfg(test)]
+#[track_caller]
+pub fn assert_money_eq(actual: Decimal, expected: &str) {
+    let expected: Decimal = expected
+        .parse()
+        .unwrap_or_else(|_| panic!("`{expected}` isn't a Decimal"));
+    assert_eq!(
+        expected.scale(),
+        2,
+        "`{expected}` isn't written to the penny"
+    );
+    assert_eq!(actual, expected, "expected {expected}, got {actual}");
+}
+
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+
     #[test]
     fn days_in_month_leap_year() {
         assert_eq!(
@@ -40,4 +207,113 @@ mod test {
             28
         );
     }
+
+    #[test]
+    fn local_date_differs_from_utc_near_midnight() {
+        let now = DateTime::<Utc>::from_str("2023-01-18T23:30:00Z").unwrap();
+        let local = FixedOffset::east_opt(2 * 3600).unwrap();
+
+        assert_eq!(
+            super::current_date(now, &local, true),
+            NaiveDate::from_ymd_opt(2023, 1, 18).unwrap()
+        );
+        assert_eq!(
+            super::current_date(now, &local, false),
+            NaiveDate::from_ymd_opt(2023, 1, 19).unwrap()
+        );
+    }
+
+    #[test]
+    fn format_date_in_two_formats() {
+        let date = NaiveDate::from_ymd_opt(2023, 2, 5).unwrap();
+
+        assert_eq!(
+            super::format_date(&date, super::DEFAULT_DATE_FORMAT).unwrap(),
+            "2023-02-05"
+        );
+        assert_eq!(super::format_date(&date, "%d/%m/%Y").unwrap(), "05/02/2023");
+    }
+
+    #[test]
+    fn invalid_date_formats_are_errors() {
+        let date = NaiveDate::from_ymd_opt(2023, 2, 5).unwrap();
+
+        assert!(super::validate_date_format("%Q").is_err());
+        assert!(super::format_date(&date, "%Q").is_err());
+        assert!(super::format_date(&date, "%H:%M").is_err());
+    }
+
+    #[test]
+    fn next_reset_dates() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+
+        assert_eq!(super::next_reset(&date(1, 10), 18), date(1, 18));
+        assert_eq!(super::next_reset(&date(1, 18), 18), date(2, 18));
+        assert_eq!(super::next_reset(&date(1, 31), 30), date(2, 28));
+    }
+
+    #[test]
+    fn money_eq_ignores_actual_scale() {
+        super::assert_money_eq(super::Decimal::new(70, 0), "70.00");
+        super::assert_money_eq(super::Decimal::new(-1234, 2), "-12.34");
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't written to the penny")]
+    fn money_eq_needs_two_decimal_places() {
+        super::assert_money_eq(super::Decimal::new(70, 0), "70");
+    }
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(super::levenshtein("kitten", "sitting"), 3);
+        assert_eq!(super::levenshtein("", "Gas"), 3);
+        assert_eq!(super::levenshtein("Water", "Water"), 0);
+    }
+
+    #[test]
+    fn closest_match_suggests_near_misses() {
+        let names = ["Gas", "Electric", "Water"];
+
+        assert_eq!(super::closest_match("Watr", names), Some("Water"));
+        assert_eq!(super::closest_match("electirc", names), Some("Electric"));
+        assert_eq!(super::closest_match("Broadband", names), None);
+    }
+
+    #[test]
+    fn next_due_dates() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+
+        assert_eq!(super::next_due(&date(1, 10), 18), date(1, 18));
+        assert_eq!(super::next_due(&date(1, 18), 18), date(1, 18));
+        assert_eq!(super::next_due(&date(1, 19), 18), date(2, 18));
+        assert_eq!(super::next_due(&date(1, 31), 30), date(2, 28));
+        assert_eq!(super::next_due(&date(2, 10), 30), date(2, 28));
+    }
+
+    #[test]
+    fn cycle_dates() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+
+        assert_eq!(super::cycle_date(&date(1, 31), 18, 6), date(2, 6));
+        assert_eq!(super::cycle_date(&date(1, 31), 18, 20), date(1, 20));
+        assert_eq!(
+            super::cycle_date(&date(1, 6), 18, 20),
+            NaiveDate::from_ymd_opt(2022, 12, 20).unwrap()
+        );
+        assert_eq!(super::cycle_date(&date(1, 6), 18, 7), date(1, 7));
+    }
+
+    #[test]
+    fn ordinals() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 28]
+            .into_iter()
+            .map(super::ordinal)
+            .collect();
+
+        assert_eq!(
+            ordinals,
+            vec!["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "28th"]
+        );
+    }
 }